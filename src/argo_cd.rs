@@ -3,78 +3,163 @@
 // This software is released under the MIT License.
 // https://opensource.org/licenses/MIT
 
-use log::{debug, info, warn};
-use reqwest::header::CONTENT_TYPE;
-use reqwest::{Client, RequestBuilder};
+use log::{debug, info, log_enabled, warn, Level};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
 use std::env;
 use std::thread::sleep;
 use std::time::{Duration, Instant};
 use tokio::runtime::{Builder, Runtime};
 use urlencoding::encode;
 
-use crate::config::{ArgoConfig, Config};
+use crate::config::{
+    ArgoAuthConfig, ArgoConfig, ArgoRolloutConfig, ArgoSessionAuthConfig,
+    ArgoStatusConditionConfig, ArgoSyncConfig, ArgoSyncOrder, ArgoSyncStrategy, Config,
+    OperationInProgressPolicy,
+};
+use crate::vault::{read_credential, Vault};
 
 const ARGO_CD_TOKEN: &str = "ARGO_CD_TOKEN";
+const ARGO_CD_PASSWORD: &str = "ARGO_CD_PASSWORD";
+
+const VAULT_TOKEN_KEY: &str = "token";
+
+const OPERATION_IN_PROGRESS: &str = "another operation is already in progress";
+const MAX_SYNC_ATTEMPTS: u32 = 5;
+
+const REFRESH_ANNOTATION: &str = "argocd.argoproj.io/refresh";
 
 pub(crate) struct ArgoCD {
     argo_config: ArgoConfig,
     client: Client,
     rt: Runtime,
+    token: Option<String>,
+    /// Only connected if the token is read from Vault.
+    vault: Option<Vault>,
+    applications: Vec<String>,
 }
 
 impl ArgoCD {
     pub(crate) fn init(config: &Config) -> ArgoCD {
         debug!("Connecting to ArgoCD at: {}", config.argo_cd.base_url);
 
-        ArgoCD {
+        let vault = match config.argo_cd.auth {
+            Some(ArgoAuthConfig::Vault(_)) => Some(Vault::connect(config)),
+            _ => None,
+        };
+
+        let mut argo_cd = ArgoCD {
             argo_config: config.argo_cd.clone(),
             client: Self::get_argocd_client(config.argo_cd.clone()),
             rt: Builder::new_current_thread()
                 .enable_all()
                 .build()
                 .expect("Failed to build ArgoCD connection"),
+            token: None,
+            vault,
+            applications: vec![],
+        };
+
+        // Missing credentials must fail before any password has been changed
+        argo_cd.token = argo_cd.get_token();
+        if argo_cd.token.is_none() {
+            if !argo_cd.argo_config.allow_unauthenticated.unwrap_or(false) {
+                panic!("Missing ArgoCD credentials: set the {ARGO_CD_TOKEN} environment variable, configure `argo_cd.auth` or set `argo_cd.allow_unauthenticated`");
+            }
+
+            warn!("You're accessing ArgoCD without authentication (missing {ARGO_CD_TOKEN} environment variable)");
+        }
+
+        // Like missing credentials, an unmatched selector must fail before any password has been changed
+        argo_cd.applications = match (
+            &config.argo_cd.application,
+            &config.argo_cd.applications,
+            &config.argo_cd.selector,
+        ) {
+            (Some(application), _, _) => vec![application.clone()],
+            (_, Some(applications), _) => applications.clone(),
+            (_, _, Some(selector)) => argo_cd.find_applications(selector),
+            _ => panic!("Missing ArgoCD application"),
+        };
+
+        for application in config.argo_cd.wait_for_manifest_change.iter().flatten() {
+            if !argo_cd.applications.contains(application) {
+                panic!("Failed to wait for the manifests of ArgoCD application '{application}': it is not rolled out");
+            }
         }
+
+        argo_cd
     }
 
-    pub(crate) fn sync(&mut self) {
-        info!(
-            "Synchronizing ArgoCD application '{}'",
-            self.argo_config.application
-        );
+    /// The applications to roll out, with a selector resolved to the matching ones.
+    pub(crate) fn applications(&self) -> &[String] {
+        &self.applications
+    }
+
+    /// Syncs all applications and waits until every one of them is rolled out.
+    pub(crate) fn rollout(&mut self) {
+        let applications = self.applications.clone();
+
+        match self.argo_config.sync_order.unwrap_or_default() {
+            ArgoSyncOrder::Parallel => {
+                for application in &applications {
+                    self.sync(application);
+                }
+                // The applications roll out at the same time, so they share one timeout
+                let deadline = self.get_sync_deadline();
+                for application in &applications {
+                    self.wait_for_rollout(application, deadline);
+                }
+            }
+            ArgoSyncOrder::Sequential => {
+                for application in &applications {
+                    self.sync(application);
+                    let deadline = self.get_sync_deadline();
+                    self.wait_for_rollout(application, deadline);
+                }
+            }
+        }
+    }
+
+    fn sync(&mut self, application: &str) {
+        if self.argo_config.hard_refresh.unwrap_or(false) {
+            self.hard_refresh(application);
+        }
+
+        info!("Synchronizing ArgoCD application '{application}'");
 
-        let app_name = self.argo_config.application.as_str();
         let url = format!(
             "{baseUrl}/api/v1/applications/{name}/sync",
             baseUrl = self.argo_config.base_url,
-            name = encode(app_name)
+            name = encode(application)
         );
 
-        let request_builder = self
-            .client
-            .post(url.as_str())
-            .header(CONTENT_TYPE, "application/json");
-        let request_builder = Self::enhance_with_authorization_token_if_applicable(request_builder);
+        let sync_request = sync_request_body(&self.argo_config.sync.clone().unwrap_or_default());
+        debug!("ArgoCD sync request: {sync_request}");
 
-        let request = request_builder
-            .build()
-            .expect("Failed to build ArgoCD sync request");
+        for attempt in 1..=MAX_SYNC_ATTEMPTS {
+            let response = self
+                .execute(|client| client.post(url.as_str()).json(&sync_request))
+                .expect("Failed to sync ArgoCD");
 
-        let response = self
-            .rt
-            .block_on(self.client.execute(request))
-            .expect("Failed to sync ArgoCD");
+            let response_status = response.status();
+            if !response_status.is_client_error() && !response_status.is_server_error() {
+                break;
+            }
 
-        let response_status = response.status();
-        if response_status.is_client_error() || response_status.is_server_error() {
             let argocd_response = self
                 .rt
                 .block_on(response.text())
                 .expect("Failed to sync ArgoCD");
-            // TODO: Think if this is problematic;
-            // Failed to sync ArgoCD: {"error":"another operation is already in progress","code":9,"message":"another operation is already in progress"}
 
-            panic!("Failed to sync ArgoCD: {argocd_response}")
+            if !is_operation_in_progress(&argocd_response) || attempt == MAX_SYNC_ATTEMPTS {
+                panic!("Failed to sync ArgoCD application '{application}': {argocd_response}")
+            }
+
+            self.handle_operation_in_progress(application, &argocd_response);
         }
 
         debug!("ArgoCD sync triggered, waiting for status update");
@@ -89,30 +174,223 @@ impl ArgoCD {
                 == "Running"
         }
 
-        self.wait_for_status_change(is_status_in_progress)
+        let deadline = self.get_sync_deadline();
+        self.wait_for_status_change(application, deadline, false, |app_information| {
+            Ok(is_status_in_progress(app_information))
+        })
     }
 
-    pub(crate) fn wait_for_rollout(&mut self) {
+    fn wait_for_rollout(&mut self, application: &str, deadline: Instant) {
         info!(
-            "Waiting for rollout of ArgoCD application '{}' to finish - timeout is {} seconds",
-            self.argo_config.application,
-            self.get_sync_timeout_seconds()
+            "Waiting for rollout of ArgoCD application '{application}' to finish - timeout is {} seconds",
+            deadline.saturating_duration_since(Instant::now()).as_secs()
         );
 
-        fn is_status_synced(app_information: &Application) -> bool {
-            app_information.status.sync.status == "Synced"
-                && app_information.status.health.status == "Healthy"
-                && (app_information.status.operationState.is_none()
-                    || app_information
-                        .status
-                        .operationState
-                        .as_ref()
-                        .map(|operation_state| operation_state.phase.clone())
-                        .unwrap_or_else(|| "NONE".to_string())
-                        == "Succeeded")
+        let rollout_config = self.get_rollout_config();
+        let success = rollout_config
+            .success
+            .unwrap_or_else(default_success_condition);
+        let failure = rollout_config
+            .failure
+            .unwrap_or_else(default_failure_condition);
+
+        self.wait_for_status_change(application, deadline, true, |app_information| {
+            rollout_status(&success, &failure, app_information)
+        })
+    }
+
+    /// Makes ArgoCD render the manifests anew, so that a plugin reading from Vault picks up the new secret version.
+    /// The refresh annotation is removed once the application has been reconciled, but only changed manifests prove
+    /// that the new secret version has been rendered.
+    fn hard_refresh(&mut self, application: &str) {
+        info!("Hard refreshing ArgoCD application '{application}'");
+
+        // Only applications rendering the secret through a Vault plugin are known to change their manifests
+        let previous_state = self
+            .argo_config
+            .wait_for_manifest_change
+            .iter()
+            .flatten()
+            .any(|name| name == application)
+            .then(|| self.get_rendered_state(application));
+
+        let url = format!(
+            "{baseUrl}/api/v1/applications/{name}?refresh=hard",
+            baseUrl = self.argo_config.base_url,
+            name = encode(application)
+        );
+
+        let response = self
+            .execute(|client| client.get(url.as_str()))
+            .expect("Failed to refresh ArgoCD application");
+
+        let response_status = response.status();
+        if !response_status.is_success() {
+            let argocd_response = self
+                .rt
+                .block_on(response.text())
+                .expect("Failed to refresh ArgoCD application");
+
+            panic!("Failed to refresh ArgoCD application '{application}': {argocd_response}")
         }
 
-        self.wait_for_status_change(is_status_synced)
+        let deadline = self.get_sync_deadline();
+        self.wait_for_status_change(application, deadline, false, |app_information| {
+            Ok(is_refreshed(app_information))
+        });
+
+        if let Some(previous_state) = previous_state {
+            self.wait_for_manifest_change(application, &previous_state, deadline);
+        }
+    }
+
+    /// Polls the rendered manifests until they differ from the ones before the refresh. Unchanged manifests don't fail
+    /// the rollout, as the sync still applies them.
+    fn wait_for_manifest_change(
+        &mut self,
+        application: &str,
+        previous_state: &RenderedState,
+        deadline: Instant,
+    ) {
+        let poll_interval =
+            Duration::from_secs(self.get_rollout_config().get_poll_interval_seconds() as u64);
+
+        loop {
+            let rendered_state = self.get_rendered_state(application);
+            if has_manifests_changed(previous_state, &rendered_state) {
+                info!("Manifests of ArgoCD application '{application}' changed");
+                return;
+            }
+
+            if Instant::now() >= deadline {
+                warn!("Manifests of ArgoCD application '{application}' did not change after the hard refresh, syncing anyway - the previous credentials may be applied");
+                return;
+            }
+
+            debug!("Manifests of ArgoCD application '{application}' did not change yet");
+            sleep(poll_interval);
+        }
+    }
+
+    fn get_rendered_state(&mut self, application: &str) -> RenderedState {
+        let base_url = self.argo_config.base_url.clone();
+        let name = encode(application);
+
+        let manifests: ManifestResponse = self.get_json(
+            format!("{base_url}/api/v1/applications/{name}/manifests").as_str(),
+            application,
+        );
+        let app_information: Application = self.get_json(
+            format!("{base_url}/api/v1/applications/{name}").as_str(),
+            application,
+        );
+
+        RenderedState {
+            manifests: manifests.manifests.unwrap_or_default(),
+            sync_status: app_information.status.sync.status,
+        }
+    }
+
+    fn get_json<T: DeserializeOwned>(&mut self, url: &str, application: &str) -> T {
+        let response = self
+            .execute(|client| client.get(url))
+            .unwrap_or_else(|e| panic!("Failed to read ArgoCD application '{application}': {e}"));
+
+        let response_status = response.status();
+        if !response_status.is_success() {
+            let argocd_response = self.rt.block_on(response.text()).unwrap_or_default();
+            panic!("Failed to read ArgoCD application '{application}': {response_status} {argocd_response}")
+        }
+
+        self.rt
+            .block_on(response.json::<T>())
+            .unwrap_or_else(|e| panic!("Failed to read ArgoCD application '{application}': {e}"))
+    }
+
+    fn handle_operation_in_progress(&mut self, application: &str, argocd_response: &str) {
+        match self.argo_config.operation_in_progress.unwrap_or_default() {
+            OperationInProgressPolicy::Wait => {
+                info!("Another operation of ArgoCD application '{application}' is in progress, waiting for it to finish");
+            }
+            OperationInProgressPolicy::Terminate => {
+                info!("Another operation of ArgoCD application '{application}' is in progress, terminating it");
+                self.terminate_operation(application);
+            }
+            OperationInProgressPolicy::Fail => {
+                panic!("Failed to sync ArgoCD application '{application}': {argocd_response}")
+            }
+        }
+
+        let deadline = self.get_sync_deadline();
+        self.wait_for_status_change(application, deadline, false, |app_information| {
+            Ok(is_operation_finished(app_information))
+        })
+    }
+
+    fn terminate_operation(&mut self, application: &str) {
+        let url = format!(
+            "{baseUrl}/api/v1/applications/{name}/operation",
+            baseUrl = self.argo_config.base_url,
+            name = encode(application)
+        );
+
+        let response = self
+            .execute(|client| client.delete(url.as_str()))
+            .expect("Failed to terminate ArgoCD operation");
+
+        let response_status = response.status();
+        if response_status.is_client_error() || response_status.is_server_error() {
+            let argocd_response = self
+                .rt
+                .block_on(response.text())
+                .expect("Failed to terminate ArgoCD operation");
+
+            panic!("Failed to terminate ArgoCD operation: {argocd_response}")
+        }
+    }
+
+    /// The names of the applications matching the configured label selector, in alphabetical order.
+    fn find_applications(&mut self, selector: &str) -> Vec<String> {
+        let url = format!(
+            "{baseUrl}/api/v1/applications?selector={selector}",
+            baseUrl = self.argo_config.base_url,
+            selector = encode(selector)
+        );
+
+        let response = self
+            .execute(|client| client.get(url.as_str()))
+            .expect("Failed to list ArgoCD applications");
+
+        let response_status = response.status();
+        if !response_status.is_success() {
+            let argocd_response = self
+                .rt
+                .block_on(response.text())
+                .expect("Failed to list ArgoCD applications");
+
+            panic!("Failed to list ArgoCD applications: {argocd_response}")
+        }
+
+        let application_list = self
+            .rt
+            .block_on(response.json::<ApplicationList>())
+            .expect("Failed to list ArgoCD applications");
+
+        let mut applications: Vec<String> = application_list
+            .items
+            .unwrap_or_default()
+            .into_iter()
+            .map(|application| application.metadata.name)
+            .collect();
+        applications.sort();
+
+        if applications.is_empty() {
+            panic!("No ArgoCD application matches selector '{selector}'");
+        }
+
+        info!("ArgoCD applications matching selector '{selector}': {applications:?}");
+
+        applications
     }
 
     fn get_argocd_client(argo_config: ArgoConfig) -> Client {
@@ -125,22 +403,167 @@ impl ArgoCD {
         }
     }
 
-    fn enhance_with_authorization_token_if_applicable(
-        request_builder: RequestBuilder,
-    ) -> RequestBuilder {
-        let argocd_token = env::var(ARGO_CD_TOKEN);
-        match argocd_token {
-            Ok(token) => {
-                debug!("Applying bearer token to ArgoCD request");
-                request_builder.header("Authorization", format!("Bearer {token}"))
+    fn get_token(&mut self) -> Option<String> {
+        match self.argo_config.auth.clone().unwrap_or_default() {
+            ArgoAuthConfig::Token => env::var(ARGO_CD_TOKEN).ok(),
+            ArgoAuthConfig::TokenFile(token_file) => Some(read_credential(
+                Some(&token_file.path),
+                ARGO_CD_TOKEN,
+                "ArgoCD token file",
+            )),
+            ArgoAuthConfig::Vault(vault_auth) => {
+                let key = vault_auth
+                    .key
+                    .clone()
+                    .unwrap_or_else(|| VAULT_TOKEN_KEY.to_string());
+                let vault = self
+                    .vault
+                    .as_mut()
+                    .expect("Missing Vault connection for ArgoCD token");
+
+                let raw_secret = match &vault_auth.mount {
+                    Some(mount) => {
+                        vault.read_raw_secret_in(mount, vault.get_kv_version(), &vault_auth.path)
+                    }
+                    None => vault.read_raw_secret(&vault_auth.path),
+                }
+                .unwrap_or_else(|e| panic!("Failed to read ArgoCD token from Vault: {e}"))
+                .unwrap_or_else(|| {
+                    panic!(
+                        "Failed to read ArgoCD token from Vault: no secret at '{}'",
+                        vault_auth.path
+                    )
+                });
+
+                let token = raw_secret.data[key.as_str()]
+                    .as_str()
+                    .unwrap_or_else(|| {
+                        panic!(
+                            "Failed to read ArgoCD token from Vault: missing key '{key}' at '{}'",
+                            vault_auth.path
+                        )
+                    })
+                    .to_string();
+                Some(token)
+            }
+            ArgoAuthConfig::Session(session) => Some(self.log_in(&session)),
+        }
+    }
+
+    /// Creates a session for a local ArgoCD account, returning its token.
+    fn log_in(&self, session: &ArgoSessionAuthConfig) -> String {
+        info!("Logging into ArgoCD as '{}'", session.username);
+
+        let password = read_credential(
+            session.password_path.as_ref(),
+            ARGO_CD_PASSWORD,
+            "ArgoCD password file",
+        );
+        let url = format!("{}/api/v1/session", self.argo_config.base_url);
+
+        let response = self
+            .rt
+            .block_on(
+                self.client
+                    .post(url.as_str())
+                    .json(&json!({"username": session.username, "password": password}))
+                    .send(),
+            )
+            .unwrap_or_else(|e| panic!("Failed to log into ArgoCD: {e}"));
+
+        let response_status = response.status();
+        if !response_status.is_success() {
+            let argocd_response = self.rt.block_on(response.text()).unwrap_or_default();
+            panic!("Failed to log into ArgoCD: {response_status} {argocd_response}");
+        }
+
+        self.rt
+            .block_on(response.json::<SessionResponse>())
+            .expect("Failed to log into ArgoCD")
+            .token
+    }
+
+    /// Tokens from the environment cannot change while propeller is running, all others are obtained anew.
+    fn is_token_renewable(&self) -> bool {
+        !matches!(
+            self.argo_config.auth.clone().unwrap_or_default(),
+            ArgoAuthConfig::Token
+        )
+    }
+
+    /// Sends the request with the current token. If ArgoCD rejects it, e.g. because a session expired during a long
+    /// rollout, the token is obtained again and the request repeated once.
+    fn execute(
+        &mut self,
+        request_builder: impl Fn(&Client) -> RequestBuilder,
+    ) -> Result<Response, reqwest::Error> {
+        let response = self.send(&request_builder)?;
+
+        if response.status() != StatusCode::UNAUTHORIZED || !self.is_token_renewable() {
+            return Ok(response);
+        }
+
+        info!("ArgoCD rejected the token, authenticating again");
+        self.token = self.get_token();
+
+        self.send(&request_builder)
+    }
+
+    fn send(
+        &self,
+        request_builder: &impl Fn(&Client) -> RequestBuilder,
+    ) -> Result<Response, reqwest::Error> {
+        let mut request_builder = request_builder(&self.client);
+        if let Some(token) = &self.token {
+            debug!("Applying bearer token to ArgoCD request");
+            request_builder = request_builder.bearer_auth(token);
+        }
+
+        self.rt.block_on(request_builder.send())
+    }
+
+    /// Lists the resources that are not healthy, read from the resource tree of the application. Empty if
+    /// `report_resources` is not set or all resources are healthy.
+    fn resource_report(&mut self, application: &str, report_resources: bool) -> String {
+        if !report_resources {
+            return String::new();
+        }
+
+        let url = format!(
+            "{baseUrl}/api/v1/applications/{name}/resource-tree",
+            baseUrl = self.argo_config.base_url,
+            name = encode(application)
+        );
+
+        let resource_tree = match self.execute(|client| client.get(url.as_str())) {
+            Ok(response) if response.status().is_success() => self
+                .rt
+                .block_on(response.json::<ApplicationTree>())
+                .map_err(|e| e.to_string()),
+            Ok(response) => Err(response.status().to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+
+        match resource_tree {
+            Ok(resource_tree) => {
+                let resources = unhealthy_resources(&resource_tree);
+                if resources.is_empty() {
+                    return String::new();
+                }
+
+                format!("\nUnhealthy resources:\n  - {}", resources.join("\n  - "))
             }
-            Err(_) => {
-                warn!("You're accessing ArgoCD without authentication (missing {ARGO_CD_TOKEN} environment variable)");
-                request_builder
+            Err(e) => {
+                warn!("Failed to read resource tree of ArgoCD application '{application}': {e}");
+                String::new()
             }
         }
     }
 
+    fn get_rollout_config(&self) -> ArgoRolloutConfig {
+        self.argo_config.rollout.clone().unwrap_or_default()
+    }
+
     fn get_sync_timeout_seconds(&self) -> u64 {
         match self.argo_config.sync_timeout_seconds {
             Some(seconds) => seconds as u64,
@@ -148,68 +571,369 @@ impl ArgoCD {
         }
     }
 
-    fn wait_for_status_change(&mut self, condition: fn(&Application) -> bool) {
+    fn get_sync_deadline(&self) -> Instant {
+        Instant::now() + Duration::from_secs(self.get_sync_timeout_seconds())
+    }
+
+    /// Polls the application until the condition is met or the deadline passed. The condition returns an error if waiting any longer is
+    /// pointless, e.g. because the operation failed. With `report_resources`, failures name the unhealthy resources
+    /// of the application, which are logged while waiting as well.
+    fn wait_for_status_change(
+        &mut self,
+        application: &str,
+        deadline: Instant,
+        report_resources: bool,
+        condition: impl Fn(&Application) -> Result<bool, String>,
+    ) {
         let url = format!(
             "{baseUrl}/api/v1/applications/{name}",
             baseUrl = self.argo_config.base_url,
-            name = encode(self.argo_config.application.as_str())
+            name = encode(application)
         );
 
-        let request_builder = self.client.get(url.as_str());
-        let request_builder = Self::enhance_with_authorization_token_if_applicable(request_builder);
-
-        let request = request_builder
-            .build()
-            .expect("Failed to build ArgoCD sync status request");
-
-        let timeout_duration = Duration::from_secs(self.get_sync_timeout_seconds());
-        let start_time = Instant::now();
+        let rollout_config = self.get_rollout_config();
+        let mut poll_interval =
+            Duration::from_secs(rollout_config.get_poll_interval_seconds() as u64);
+        let max_poll_interval =
+            Duration::from_secs(rollout_config.get_max_poll_interval_seconds() as u64);
+        let poll_backoff_factor = rollout_config.get_poll_backoff_factor();
 
         loop {
-            if start_time.elapsed() >= timeout_duration {
-                panic!("Timeout reached while waiting for ArgoCD sync status");
+            if Instant::now() >= deadline {
+                let resource_report = self.resource_report(application, report_resources);
+                panic!("Timeout reached while waiting for ArgoCD sync status of application '{application}'{resource_report}");
             }
 
             let response = self
-                .rt
-                .block_on(
-                    self.client.execute(
-                        request
-                            .try_clone()
-                            .expect("Failed to build ArgoCD sync status request"),
-                    ),
-                )
+                .execute(|client| client.get(url.as_str()))
                 .expect("Failed to request ArgoCD sync status");
 
             if response.status().is_success() {
-                let app_information = match self.rt.block_on(response.json::<Application>()).ok() {
-                    Some(app) => app,
-                    None => continue,
-                };
-
-                debug!("ArgoCD sync status response: {app_information:?}");
-
-                if condition(&app_information) {
-                    info!("Desired ArgoCD application status met");
-                    return;
-                } else {
-                    let status = app_information.status;
-                    debug!(
-                        "ArgoCD application did not meet desired status yet: {{ 'sync': '{}', 'health': '{}', 'operation_state': '{}' }}",
-                        status.sync.status, status.health.status, status.operationState.map(|o| o.phase).unwrap_or_else(|| "None".to_string())
-                    );
+                match self.rt.block_on(response.json::<Application>()) {
+                    Ok(app_information) => {
+                        debug!("ArgoCD sync status response: {app_information:?}");
+
+                        match condition(&app_information) {
+                            Ok(true) => {
+                                info!("Desired status of ArgoCD application '{application}' met");
+                                return;
+                            }
+                            Ok(false) => {
+                                let status = app_information.status;
+                                debug!(
+                                    "ArgoCD application did not meet desired status yet: {{ 'sync': '{}', 'health': '{}', 'operation_state': '{}' }}",
+                                    status.sync.status, status.health.status, status.operationState.map(|o| o.phase).unwrap_or_else(|| "None".to_string())
+                                );
+
+                                if log_enabled!(Level::Debug) {
+                                    let resource_report =
+                                        self.resource_report(application, report_resources);
+                                    if !resource_report.is_empty() {
+                                        debug!("ArgoCD application '{application}' is not ready yet{resource_report}");
+                                    }
+                                }
+                            }
+                            Err(report) => {
+                                let resource_report =
+                                    self.resource_report(application, report_resources);
+                                panic!("ArgoCD application '{application}' failed: {report}{resource_report}")
+                            }
+                        }
+                    }
+                    Err(e) => debug!("Failed to parse application status: {e}"),
                 }
             } else {
                 debug!("Failed to get application status: {}", response.status());
             }
 
-            sleep(Duration::from_secs(5));
+            sleep(poll_interval);
+            poll_interval = poll_interval
+                .mul_f64(poll_backoff_factor)
+                .min(max_poll_interval);
         }
     }
 }
 
+/// The body of the sync request, see `ApplicationSyncRequest` in the ArgoCD API. Unset options are left to ArgoCD.
+fn sync_request_body(sync_config: &ArgoSyncConfig) -> Value {
+    let mut sync_request = Map::new();
+
+    if let Some(prune) = sync_config.prune {
+        sync_request.insert("prune".to_string(), json!(prune));
+    }
+    if let Some(revision) = &sync_config.revision {
+        sync_request.insert("revision".to_string(), json!(revision));
+    }
+    if let Some(strategy) = sync_config.strategy {
+        let strategy_name = match strategy {
+            ArgoSyncStrategy::Apply => "apply",
+            ArgoSyncStrategy::Hook => "hook",
+        };
+        sync_request.insert(
+            "strategy".to_string(),
+            json!({strategy_name: {"force": sync_config.force.unwrap_or(false)}}),
+        );
+    }
+    if let Some(sync_options) = &sync_config.sync_options {
+        sync_request.insert("syncOptions".to_string(), json!({"items": sync_options}));
+    }
+    if let Some(resources) = &sync_config.resources {
+        let resources: Vec<Value> = resources
+            .iter()
+            .map(|resource| {
+                json!({
+                    "group": resource.group.clone().unwrap_or_default(),
+                    "kind": resource.kind,
+                    "name": resource.name,
+                    "namespace": resource.namespace.clone().unwrap_or_default()
+                })
+            })
+            .collect();
+        sync_request.insert("resources".to_string(), json!(resources));
+    }
+
+    Value::Object(sync_request)
+}
+
+/// ArgoCD refuses to sync while another operation, e.g. an auto-sync, is running.
+fn is_operation_in_progress(argocd_response: &str) -> bool {
+    argocd_response.contains(OPERATION_IN_PROGRESS)
+}
+
+fn default_success_condition() -> ArgoStatusConditionConfig {
+    ArgoStatusConditionConfig {
+        sync_status: Some(vec!["Synced".to_string()]),
+        health_status: Some(vec!["Healthy".to_string()]),
+        operation_phase: Some(vec!["Succeeded".to_string()]),
+    }
+}
+
+fn default_failure_condition() -> ArgoStatusConditionConfig {
+    ArgoStatusConditionConfig {
+        sync_status: None,
+        health_status: Some(vec!["Degraded".to_string()]),
+        operation_phase: Some(vec!["Failed".to_string(), "Error".to_string()]),
+    }
+}
+
+/// Whether the rollout succeeded, or an error if it failed.
+fn rollout_status(
+    success: &ArgoStatusConditionConfig,
+    failure: &ArgoStatusConditionConfig,
+    app_information: &Application,
+) -> Result<bool, String> {
+    // The health reported while the sync is running may still be the one of the previous revision
+    if !is_operation_finished(app_information) {
+        return Ok(false);
+    }
+
+    if is_any_condition_met(failure, app_information) {
+        return Err(failure_report(app_information));
+    }
+
+    Ok(is_condition_met(success, app_information))
+}
+
+/// Whether all configured fields match. An application without operation, e.g. one that has never been synced
+/// manually, matches any operation phase.
+fn is_condition_met(condition: &ArgoStatusConditionConfig, app_information: &Application) -> bool {
+    let status = &app_information.status;
+
+    matches_value(&condition.sync_status, &status.sync.status).unwrap_or(true)
+        && matches_value(&condition.health_status, &status.health.status).unwrap_or(true)
+        && status
+            .operationState
+            .as_ref()
+            .and_then(|operation_state| {
+                matches_value(&condition.operation_phase, &operation_state.phase)
+            })
+            .unwrap_or(true)
+}
+
+/// Whether any configured field matches, used to detect terminal failures.
+fn is_any_condition_met(
+    condition: &ArgoStatusConditionConfig,
+    app_information: &Application,
+) -> bool {
+    let status = &app_information.status;
+
+    matches_value(&condition.sync_status, &status.sync.status).unwrap_or(false)
+        || matches_value(&condition.health_status, &status.health.status).unwrap_or(false)
+        || status
+            .operationState
+            .as_ref()
+            .and_then(|operation_state| {
+                matches_value(&condition.operation_phase, &operation_state.phase)
+            })
+            .unwrap_or(false)
+}
+
+/// `None` if no values are configured.
+fn matches_value(values: &Option<Vec<String>>, value: &str) -> Option<bool> {
+    values
+        .as_ref()
+        .map(|values| values.iter().any(|expected| expected == value))
+}
+
+/// Describes the status of a failed application, along with the resources that failed to sync or are degraded.
+fn failure_report(app_information: &Application) -> String {
+    let status = &app_information.status;
+    let operation_state = status.operationState.as_ref();
+
+    let mut report = format!(
+        "sync status '{}', health status '{}', operation phase '{}'",
+        status.sync.status,
+        status.health.status,
+        operation_state.map_or("None", |operation_state| operation_state.phase.as_str())
+    );
+    if let Some(message) =
+        operation_state.and_then(|operation_state| operation_state.message.as_ref())
+    {
+        report.push_str(&format!(" - {message}"));
+    }
+
+    let sync_failures = operation_state
+        .and_then(|operation_state| operation_state.syncResult.as_ref())
+        .and_then(|sync_result| sync_result.resources.as_ref())
+        .into_iter()
+        .flatten()
+        .filter(|resource| {
+            resource.status.as_deref() == Some("SyncFailed")
+                || matches!(resource.hookPhase.as_deref(), Some("Failed" | "Error"))
+        })
+        .map(|resource| {
+            describe_resource(
+                &resource.kind,
+                resource.namespace.as_deref(),
+                &resource.name,
+                resource.message.as_deref().unwrap_or("sync failed"),
+            )
+        });
+    let health_failures = status
+        .resources
+        .iter()
+        .flatten()
+        .filter(|resource| {
+            resource
+                .health
+                .as_ref()
+                .and_then(|health| health.status.as_deref())
+                == Some("Degraded")
+        })
+        .map(|resource| {
+            describe_resource(
+                &resource.kind,
+                resource.namespace.as_deref(),
+                &resource.name,
+                resource
+                    .health
+                    .as_ref()
+                    .and_then(|health| health.message.as_deref())
+                    .unwrap_or("degraded"),
+            )
+        });
+
+    for failure in sync_failures.chain(health_failures) {
+        report.push_str(&format!("\n  - {failure}"));
+    }
+
+    report
+}
+
+/// The resources of the tree that report a health other than `Healthy`, e.g. a Pod in `CrashLoopBackOff`.
+fn unhealthy_resources(resource_tree: &ApplicationTree) -> Vec<String> {
+    resource_tree
+        .nodes
+        .iter()
+        .flatten()
+        .filter_map(|node| {
+            let health = node.health.as_ref()?;
+            let health_status = health
+                .status
+                .as_deref()
+                .filter(|status| *status != "Healthy")?;
+            let message = match &health.message {
+                Some(message) => format!("{health_status} - {message}"),
+                None => health_status.to_string(),
+            };
+
+            Some(describe_resource(
+                &node.kind,
+                node.namespace.as_deref(),
+                &node.name,
+                &message,
+            ))
+        })
+        .collect()
+}
+
+fn describe_resource(kind: &str, namespace: Option<&str>, name: &str, message: &str) -> String {
+    match namespace.filter(|namespace| !namespace.is_empty()) {
+        Some(namespace) => format!("{kind} '{namespace}/{name}': {message}"),
+        None => format!("{kind} '{name}': {message}"),
+    }
+}
+
+fn is_refreshed(app_information: &Application) -> bool {
+    !app_information
+        .metadata
+        .annotations
+        .as_ref()
+        .is_some_and(|annotations| annotations.contains_key(REFRESH_ANNOTATION))
+}
+
+/// ArgoCD masks the values of Secrets in the rendered manifests, so a changed Secret only shows as the application
+/// going out of sync with its live state.
+fn has_manifests_changed(previous_state: &RenderedState, rendered_state: &RenderedState) -> bool {
+    rendered_state.manifests != previous_state.manifests
+        || (previous_state.sync_status == "Synced" && rendered_state.sync_status == "OutOfSync")
+}
+
+fn is_operation_finished(app_information: &Application) -> bool {
+    app_information
+        .status
+        .operationState
+        .as_ref()
+        .is_none_or(|operation_state| {
+            !matches!(operation_state.phase.as_str(), "Running" | "Terminating")
+        })
+}
+
+#[derive(Deserialize)]
+struct ApplicationList {
+    items: Option<Vec<ApplicationListItem>>,
+}
+
+#[derive(Deserialize)]
+struct ApplicationListItem {
+    metadata: ApplicationMetadata,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApplicationMetadata {
+    name: String,
+    annotations: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Deserialize)]
+struct ManifestResponse {
+    manifests: Option<Vec<String>>,
+}
+
+/// The manifests rendered by ArgoCD, along with whether they match the live state.
+struct RenderedState {
+    manifests: Vec<String>,
+    sync_status: String,
+}
+
+#[derive(Deserialize)]
+struct SessionResponse {
+    token: String,
+}
+
 #[derive(Debug, Deserialize)]
 struct Application {
+    metadata: ApplicationMetadata,
     status: ApplicationStatus,
 }
 
@@ -219,6 +943,7 @@ struct ApplicationStatus {
     sync: SyncStatus,
     health: HealthStatus,
     operationState: Option<OperationState>,
+    resources: Option<Vec<ResourceStatus>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -231,10 +956,408 @@ struct HealthStatus {
     status: String,
 }
 
+#[allow(non_snake_case)]
 #[derive(Debug, Deserialize)]
 struct OperationState {
     phase: String,
+    message: Option<String>,
+    syncResult: Option<SyncOperationResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SyncOperationResult {
+    resources: Option<Vec<ResourceResult>>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize)]
+struct ResourceResult {
+    kind: String,
+    namespace: Option<String>,
+    name: String,
+    status: Option<String>,
+    message: Option<String>,
+    hookPhase: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResourceStatus {
+    kind: String,
+    namespace: Option<String>,
+    name: String,
+    health: Option<ResourceHealth>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApplicationTree {
+    nodes: Option<Vec<ResourceNode>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResourceNode {
+    kind: String,
+    namespace: Option<String>,
+    name: String,
+    health: Option<ResourceHealth>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResourceHealth {
+    status: Option<String>,
+    message: Option<String>,
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    use std::env::temp_dir;
+    use std::fs::write;
+    use std::path::PathBuf;
+
+    use crate::config::{ArgoSyncResourceConfig, PostgresConfig, TokenFileAuthConfig, VaultConfig};
+
+    #[test]
+    fn argo_cd_init_token_from_environment() {
+        let config = create_config(None);
+        env::set_var(ARGO_CD_TOKEN, "env-token"); // Mock environment variable
+
+        let argo_cd = ArgoCD::init(&config);
+
+        assert_eq!(argo_cd.token, Some("env-token".to_string()));
+        assert!(!argo_cd.is_token_renewable());
+    }
+
+    #[test]
+    fn argo_cd_init_token_file() {
+        let config = create_config(Some(ArgoAuthConfig::TokenFile(TokenFileAuthConfig {
+            path: write_token_file("file-token\n"),
+        })));
+
+        let argo_cd = ArgoCD::init(&config);
+
+        assert_eq!(argo_cd.token, Some("file-token".to_string()));
+        assert!(argo_cd.is_token_renewable());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to read ArgoCD token file 'tests/resources/non_existing_token'"
+    )]
+    fn argo_cd_init_missing_token_file() {
+        let config = create_config(Some(ArgoAuthConfig::TokenFile(TokenFileAuthConfig {
+            path: PathBuf::from("tests/resources/non_existing_token"),
+        })));
+
+        ArgoCD::init(&config); // This should panic
+    }
+
+    #[test]
+    #[should_panic(expected = "Missing ARGO_CD_PASSWORD environment variable")]
+    fn argo_cd_init_session_missing_password() {
+        let config = create_config(Some(ArgoAuthConfig::Session(ArgoSessionAuthConfig {
+            username: "propeller".to_string(),
+            password_path: None,
+        })));
+        env::remove_var(ARGO_CD_PASSWORD); // Ensure ARGO_CD_PASSWORD is not present
+
+        ArgoCD::init(&config); // This should panic
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to read ArgoCD password file 'tests/resources/non_existing_password'"
+    )]
+    fn argo_cd_init_session_missing_password_file() {
+        let config = create_config(Some(ArgoAuthConfig::Session(ArgoSessionAuthConfig {
+            username: "propeller".to_string(),
+            password_path: Some(PathBuf::from("tests/resources/non_existing_password")),
+        })));
+
+        ArgoCD::init(&config); // This should panic
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to wait for the manifests of ArgoCD application 'other': it is not rolled out"
+    )]
+    fn argo_cd_init_wait_for_manifest_change_of_other_application() {
+        let mut config = create_config(None);
+        config.argo_cd.hard_refresh = Some(true);
+        config.argo_cd.wait_for_manifest_change = Some(vec!["other".to_string()]);
+        env::set_var(ARGO_CD_TOKEN, "env-token"); // Mock environment variable
+
+        ArgoCD::init(&config); // This should panic
+    }
+
+    #[test]
+    fn operation_in_progress_response() {
+        assert!(is_operation_in_progress(
+            r#"{"error":"another operation is already in progress","code":9,"message":"another operation is already in progress"}"#
+        ));
+        assert!(!is_operation_in_progress(
+            r#"{"error":"permission denied","code":7,"message":"permission denied"}"#
+        ));
+    }
+
+    #[test]
+    fn operation_finished() {
+        assert!(is_operation_finished(&create_application(None)));
+        assert!(is_operation_finished(&create_application(Some(
+            "Succeeded"
+        ))));
+        assert!(is_operation_finished(&create_application(Some("Failed"))));
+        assert!(!is_operation_finished(&create_application(Some("Running"))));
+        assert!(!is_operation_finished(&create_application(Some(
+            "Terminating"
+        ))));
+    }
+
+    #[test]
+    fn default_conditions() {
+        let success = default_success_condition();
+        let failure = default_failure_condition();
+
+        let mut application = create_application(Some("Succeeded"));
+        application.status.sync.status = "Synced".to_string();
+        assert!(is_condition_met(&success, &application));
+        assert!(!is_any_condition_met(&failure, &application));
+
+        // Never synced by an operation
+        application.status.operationState = None;
+        assert!(is_condition_met(&success, &application));
+
+        application.status.health.status = "Progressing".to_string();
+        assert!(!is_condition_met(&success, &application));
+        assert!(!is_any_condition_met(&failure, &application));
+
+        application.status.health.status = "Degraded".to_string();
+        assert!(is_any_condition_met(&failure, &application));
+
+        let application = create_application(Some("Error"));
+        assert!(is_any_condition_met(&failure, &application));
+    }
+
+    #[test]
+    fn rollout_status_waits_for_operation() {
+        let success = default_success_condition();
+        let failure = default_failure_condition();
+
+        let mut application = create_application(Some("Running"));
+        application.status.sync.status = "Synced".to_string();
+        assert_eq!(rollout_status(&success, &failure, &application), Ok(false));
+
+        application.status.health.status = "Degraded".to_string();
+        assert_eq!(rollout_status(&success, &failure, &application), Ok(false));
+
+        let mut application = create_application(Some("Succeeded"));
+        application.status.sync.status = "Synced".to_string();
+        assert_eq!(rollout_status(&success, &failure, &application), Ok(true));
+
+        let application = create_application(Some("Failed"));
+        assert!(rollout_status(&success, &failure, &application).is_err());
+    }
+
+    #[test]
+    fn configured_conditions() {
+        let success = ArgoStatusConditionConfig {
+            sync_status: None,
+            health_status: Some(vec!["Healthy".to_string(), "Suspended".to_string()]),
+            operation_phase: None,
+        };
+        let failure = ArgoStatusConditionConfig::default();
+
+        let mut application = create_application(Some("Failed"));
+        application.status.health.status = "Suspended".to_string();
+
+        assert!(is_condition_met(&success, &application));
+        assert!(!is_any_condition_met(&failure, &application));
+    }
+
+    #[test]
+    fn failure_report_lists_failing_resources() {
+        let mut application = create_application(Some("Failed"));
+        application.status.health.status = "Degraded".to_string();
+
+        let operation_state = application.status.operationState.as_mut().unwrap();
+        operation_state.message = Some("one or more objects failed to apply".to_string());
+        operation_state.syncResult = Some(SyncOperationResult {
+            resources: Some(vec![
+                ResourceResult {
+                    kind: "Deployment".to_string(),
+                    namespace: Some("demo".to_string()),
+                    name: "api".to_string(),
+                    status: Some("SyncFailed".to_string()),
+                    message: Some("invalid spec".to_string()),
+                    hookPhase: None,
+                },
+                ResourceResult {
+                    kind: "Secret".to_string(),
+                    namespace: Some("demo".to_string()),
+                    name: "database".to_string(),
+                    status: Some("Synced".to_string()),
+                    message: None,
+                    hookPhase: None,
+                },
+            ]),
+        });
+        application.status.resources = Some(vec![ResourceStatus {
+            kind: "ClusterRole".to_string(),
+            namespace: None,
+            name: "worker".to_string(),
+            health: Some(ResourceHealth {
+                status: Some("Degraded".to_string()),
+                message: None,
+            }),
+        }]);
+
+        assert_eq!(
+            failure_report(&application),
+            "sync status 'OutOfSync', health status 'Degraded', operation phase 'Failed' - one or more objects failed to apply\n  - Deployment 'demo/api': invalid spec\n  - ClusterRole 'worker': degraded"
+        );
+    }
+
+    #[test]
+    fn refreshed() {
+        let mut application = create_application(None);
+        assert!(is_refreshed(&application));
+
+        application.metadata.annotations = Some(BTreeMap::from([(
+            REFRESH_ANNOTATION.to_string(),
+            "hard".to_string(),
+        )]));
+        assert!(!is_refreshed(&application));
+    }
+
+    #[test]
+    fn manifests_changed() {
+        let rendered_state = |manifests: &[&str], sync_status: &str| RenderedState {
+            manifests: manifests
+                .iter()
+                .map(|manifest| manifest.to_string())
+                .collect(),
+            sync_status: sync_status.to_string(),
+        };
+        let previous_state = rendered_state(&["{\"kind\":\"Secret\"}"], "Synced");
+
+        assert!(!has_manifests_changed(
+            &previous_state,
+            &rendered_state(&["{\"kind\":\"Secret\"}"], "Synced")
+        ));
+        assert!(has_manifests_changed(
+            &previous_state,
+            &rendered_state(&["{\"kind\":\"ConfigMap\"}"], "Synced")
+        ));
+        assert!(has_manifests_changed(
+            &previous_state,
+            &rendered_state(&["{\"kind\":\"Secret\"}"], "OutOfSync")
+        ));
+        assert!(!has_manifests_changed(
+            &rendered_state(&["{\"kind\":\"Secret\"}"], "OutOfSync"),
+            &rendered_state(&["{\"kind\":\"Secret\"}"], "OutOfSync")
+        ));
+    }
+
+    #[test]
+    fn unhealthy_resources_from_tree() {
+        let resource_tree: ApplicationTree = serde_json::from_value(json!({
+            "nodes": [
+                {"kind": "Deployment", "namespace": "demo", "name": "api", "health": {"status": "Progressing", "message": "Waiting for rollout to finish"}},
+                {"kind": "ReplicaSet", "namespace": "demo", "name": "api-7d9f", "health": {"status": "Healthy"}},
+                {"kind": "Pod", "namespace": "demo", "name": "api-7d9f-x2k4p", "health": {"status": "Degraded", "message": "CrashLoopBackOff"}},
+                {"kind": "ConfigMap", "namespace": "demo", "name": "api-config"},
+                {"kind": "Namespace", "name": "demo", "health": {"status": "Missing"}}
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            unhealthy_resources(&resource_tree),
+            vec![
+                "Deployment 'demo/api': Progressing - Waiting for rollout to finish",
+                "Pod 'demo/api-7d9f-x2k4p': Degraded - CrashLoopBackOff",
+                "Namespace 'demo': Missing",
+            ]
+        );
+        assert!(unhealthy_resources(&ApplicationTree { nodes: None }).is_empty());
+    }
+
+    #[test]
+    fn sync_request_body_without_options() {
+        assert_eq!(sync_request_body(&ArgoSyncConfig::default()), json!({}));
+    }
+
+    #[test]
+    fn sync_request_body_with_options() {
+        let sync_config = ArgoSyncConfig {
+            prune: Some(true),
+            revision: Some("main".to_string()),
+            strategy: Some(ArgoSyncStrategy::Hook),
+            force: None,
+            sync_options: Some(vec!["ApplyOutOfSyncOnly=true".to_string()]),
+            resources: Some(vec![ArgoSyncResourceConfig {
+                group: Some("apps".to_string()),
+                kind: "Deployment".to_string(),
+                name: "demo".to_string(),
+                namespace: None,
+            }]),
+        };
+
+        assert_eq!(
+            sync_request_body(&sync_config),
+            json!({
+                "prune": true,
+                "revision": "main",
+                "strategy": {"hook": {"force": false}},
+                "syncOptions": {"items": ["ApplyOutOfSyncOnly=true"]},
+                "resources": [{"group": "apps", "kind": "Deployment", "name": "demo", "namespace": ""}]
+            })
+        );
+    }
+
+    fn create_application(phase: Option<&str>) -> Application {
+        Application {
+            metadata: ApplicationMetadata {
+                name: "propeller".to_string(),
+                annotations: None,
+            },
+            status: ApplicationStatus {
+                sync: SyncStatus {
+                    status: "OutOfSync".to_string(),
+                },
+                health: HealthStatus {
+                    status: "Healthy".to_string(),
+                },
+                operationState: phase.map(|phase| OperationState {
+                    phase: phase.to_string(),
+                    message: None,
+                    syncResult: None,
+                }),
+                resources: None,
+            },
+        }
+    }
+
+    fn create_config(auth: Option<ArgoAuthConfig>) -> Config {
+        Config {
+            argo_cd: ArgoConfig {
+                auth,
+                ..ArgoConfig::default()
+            },
+            aws_secrets_manager: None,
+            cassandra: None,
+            clickhouse: None,
+            kubernetes: None,
+            lock: None,
+            plugin: None,
+            postgres: Some(PostgresConfig::default()),
+            vault: Some(VaultConfig::default()),
+        }
+    }
+
+    fn write_token_file(content: &str) -> PathBuf {
+        let path = temp_dir().join(format!("propeller_argo_cd_token_{}", rand::random::<u64>()));
+        write(&path, content).expect("Failed to write token file");
+        path
+    }
+}
@@ -0,0 +1,352 @@
+// Copyright (c) 2024 PostFinance AG
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+
+use std::collections::HashMap;
+
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_secretsmanager::error::DisplayErrorContext;
+use aws_sdk_secretsmanager::Client;
+use log::{debug, info, warn};
+use serde_json::{Map, Value};
+use tokio::runtime::{Builder, Runtime};
+
+use crate::config::{AwsSecretsManagerConfig, Config, VaultKeysConfig};
+use crate::secret_store::SecretStore;
+use crate::vault::VaultStructure;
+
+const AWS_CURRENT: &str = "AWSCURRENT";
+const AWS_PENDING: &str = "AWSPENDING";
+
+/// Stores the users and passwords as JSON in an AWS Secrets Manager secret. Like a rotation Lambda, the rotation stages
+/// a new version as `AWSPENDING` before changing the database, and promotes it to `AWSCURRENT` after the change
+/// succeeded.
+pub(crate) struct AwsSecretsManager {
+    aws_config: AwsSecretsManagerConfig,
+    client: Client,
+    rt: Runtime,
+    /// The version staged by this run, promoted by the next write of the same secret.
+    staged: Option<StagedSecret>,
+}
+
+/// The `AWSCURRENT` version of the secret.
+struct CurrentSecret {
+    version_id: String,
+    data: Value,
+}
+
+struct StagedSecret {
+    version_id: String,
+    data: Value,
+}
+
+impl AwsSecretsManager {
+    /// Credentials and, unless configured, the region are resolved by the default provider chain of the AWS SDK: the
+    /// environment, the shared config files, a web identity, the container or the instance metadata.
+    pub(crate) fn connect(config: &Config) -> AwsSecretsManager {
+        let aws_config = config
+            .aws_secrets_manager
+            .clone()
+            .expect("Missing AWS Secrets Manager configuration");
+
+        let rt = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to build AWS Secrets Manager connection");
+
+        let mut config_loader = aws_config::defaults(BehaviorVersion::latest());
+        if let Some(region) = &aws_config.region {
+            config_loader = config_loader.region(Region::new(region.clone()));
+        }
+        if let Some(endpoint) = &aws_config.endpoint {
+            debug!("Connecting to AWS Secrets Manager at: {endpoint}");
+            config_loader = config_loader.endpoint_url(endpoint);
+        }
+
+        let sdk_config = rt.block_on(config_loader.load());
+        if sdk_config.region().is_none() {
+            panic!("Missing AWS region, set `aws_secrets_manager.region` or the AWS_REGION environment variable");
+        }
+
+        AwsSecretsManager {
+            aws_config,
+            client: Client::new(&sdk_config),
+            rt,
+            staged: None,
+        }
+    }
+
+    fn read_current_secret(&self) -> Result<Option<CurrentSecret>, String> {
+        let response = match self.rt.block_on(
+            self.client
+                .get_secret_value()
+                .secret_id(&self.aws_config.secret_id)
+                .version_stage(AWS_CURRENT)
+                .send(),
+        ) {
+            Ok(response) => response,
+            Err(e)
+                if e.as_service_error()
+                    .is_some_and(|e| e.is_resource_not_found_exception()) =>
+            {
+                return Ok(None)
+            }
+            Err(e) => return Err(DisplayErrorContext(e).to_string()),
+        };
+
+        let version_id = response
+            .version_id()
+            .ok_or("Missing version ID of AWS secret")?
+            .to_string();
+        let data = serde_json::from_str(response.secret_string().unwrap_or("{}"))
+            .map_err(|e| format!("Failed to parse AWS secret as JSON: {e}"))?;
+
+        Ok(Some(CurrentSecret { version_id, data }))
+    }
+
+    /// A version still staged as `AWSPENDING`, but not current, is left behind by a run interrupted before promoting it.
+    fn find_pending_version_id(&self) -> Result<Option<String>, String> {
+        let description = self
+            .rt
+            .block_on(
+                self.client
+                    .describe_secret()
+                    .secret_id(&self.aws_config.secret_id)
+                    .send(),
+            )
+            .map_err(|e| DisplayErrorContext(e).to_string())?;
+
+        Ok(description
+            .version_ids_to_stages()
+            .and_then(find_pending_version))
+    }
+
+    /// Merges the users and passwords into the current secret, so that other keys stored in it survive the write.
+    fn merge_into_current_secret(
+        &self,
+        vault_structure: &VaultStructure,
+    ) -> Result<(Option<CurrentSecret>, Value), String> {
+        let current = self.read_current_secret()?;
+
+        let mut secret = current
+            .as_ref()
+            .map(|current| current.data.clone())
+            .unwrap_or_else(|| Value::Object(Map::new()));
+        vault_structure.to_secret(&mut secret, &self.get_keys())?;
+
+        Ok((current, secret))
+    }
+
+    fn put_pending_version(&self, secret: &Value) -> Result<String, String> {
+        let response = self
+            .rt
+            .block_on(
+                self.client
+                    .put_secret_value()
+                    .secret_id(&self.aws_config.secret_id)
+                    .secret_string(secret.to_string())
+                    .version_stages(AWS_PENDING)
+                    .send(),
+            )
+            .map_err(|e| DisplayErrorContext(e).to_string())?;
+
+        Ok(response
+            .version_id()
+            .ok_or("Missing version ID of staged AWS secret")?
+            .to_string())
+    }
+
+    fn get_keys(&self) -> VaultKeysConfig {
+        self.aws_config.keys.clone().unwrap_or_default()
+    }
+}
+
+impl SecretStore for AwsSecretsManager {
+    fn location(&self) -> String {
+        self.aws_config.secret_id.clone()
+    }
+
+    fn init_secret_path(&mut self) {
+        info!("Initializing AWS secret '{}'", self.location());
+
+        let vault_structure = VaultStructure {
+            postgresql_active_user: "TBD".to_string(),
+            postgresql_active_user_password: "TBD".to_string(),
+            postgresql_user_1: "TBD".to_string(),
+            postgresql_user_1_password: "TBD".to_string(),
+            postgresql_user_2: "TBD".to_string(),
+            postgresql_user_2_password: "TBD".to_string(),
+        };
+
+        self.write_secret(&vault_structure)
+            .expect("Failed to create initial AWS secret");
+
+        println!("Successfully initialized AWS secret '{}'", self.location())
+    }
+
+    fn read_secret(&mut self) -> Result<VaultStructure, String> {
+        info!("Reading AWS secret '{}'", self.location());
+
+        let current = self
+            .read_current_secret()?
+            .ok_or_else(|| format!("No AWS secret '{}' found", self.location()))?;
+
+        VaultStructure::from_secret(&current.data, &self.get_keys())
+    }
+
+    fn write_secret(&mut self, vault_structure: &VaultStructure) -> Result<(), String> {
+        info!("Writing AWS secret '{}'", self.location());
+
+        let secret_id = self.aws_config.secret_id.clone();
+        let (current, secret) = self.merge_into_current_secret(vault_structure)?;
+
+        let Some(current) = current else {
+            self.rt
+                .block_on(
+                    self.client
+                        .create_secret()
+                        .name(&secret_id)
+                        .secret_string(secret.to_string())
+                        .send(),
+                )
+                .map_err(|e| DisplayErrorContext(e).to_string())?;
+            return Ok(());
+        };
+
+        // Writes without a database change, like initializing the secret, stage their version right away
+        let version_id = match self.staged.take() {
+            Some(staged) if staged.data == secret => staged.version_id,
+            _ => self.put_pending_version(&secret)?,
+        };
+
+        debug!("Promoting version '{version_id}' from {AWS_PENDING} to {AWS_CURRENT}");
+
+        // Fails if another version became current in the meantime; Secrets Manager labels the replaced one AWSPREVIOUS
+        self.rt
+            .block_on(
+                self.client
+                    .update_secret_version_stage()
+                    .secret_id(&secret_id)
+                    .version_stage(AWS_CURRENT)
+                    .move_to_version_id(&version_id)
+                    .remove_from_version_id(current.version_id)
+                    .send(),
+            )
+            .map_err(|e| DisplayErrorContext(e).to_string())?;
+        self.rt
+            .block_on(
+                self.client
+                    .update_secret_version_stage()
+                    .secret_id(&secret_id)
+                    .version_stage(AWS_PENDING)
+                    .remove_from_version_id(&version_id)
+                    .send(),
+            )
+            .map_err(|e| DisplayErrorContext(e).to_string())?;
+
+        Ok(())
+    }
+
+    fn stage_secret(&mut self, vault_structure: &VaultStructure) -> Result<(), String> {
+        let (current, secret) = self.merge_into_current_secret(vault_structure)?;
+        if current.is_none() {
+            return Err(format!("No AWS secret '{}' found", self.location()));
+        }
+
+        // Replaces the AWSPENDING label of any version staged earlier
+        let version_id = self.put_pending_version(&secret)?;
+
+        debug!("Staged version '{version_id}' as {AWS_PENDING}");
+
+        self.staged = Some(StagedSecret {
+            version_id,
+            data: secret,
+        });
+
+        Ok(())
+    }
+
+    fn read_pending_secret(&mut self) -> Result<Option<VaultStructure>, String> {
+        let Some(version_id) = self.find_pending_version_id()? else {
+            return Ok(None);
+        };
+
+        warn!(
+            "AWS secret '{}' has version '{version_id}' staged as {AWS_PENDING} by an interrupted run",
+            self.location()
+        );
+
+        let response = self
+            .rt
+            .block_on(
+                self.client
+                    .get_secret_value()
+                    .secret_id(&self.aws_config.secret_id)
+                    .version_id(&version_id)
+                    .send(),
+            )
+            .map_err(|e| DisplayErrorContext(e).to_string())?;
+        let data = serde_json::from_str(response.secret_string().unwrap_or("{}"))
+            .map_err(|e| format!("Failed to parse AWS secret as JSON: {e}"))?;
+
+        VaultStructure::from_secret(&data, &self.get_keys()).map(Some)
+    }
+
+    fn discard_pending_secret(&mut self) -> Result<(), String> {
+        let Some(version_id) = self.find_pending_version_id()? else {
+            return Ok(());
+        };
+
+        self.rt
+            .block_on(
+                self.client
+                    .update_secret_version_stage()
+                    .secret_id(&self.aws_config.secret_id)
+                    .version_stage(AWS_PENDING)
+                    .remove_from_version_id(&version_id)
+                    .send(),
+            )
+            .map_err(|e| DisplayErrorContext(e).to_string())?;
+
+        info!("Discarded version '{version_id}' staged as {AWS_PENDING}");
+
+        Ok(())
+    }
+}
+
+fn find_pending_version(version_ids_to_stages: &HashMap<String, Vec<String>>) -> Option<String> {
+    version_ids_to_stages
+        .iter()
+        .find(|(_, stages)| {
+            stages.iter().any(|stage| stage == AWS_PENDING)
+                && !stages.iter().any(|stage| stage == AWS_CURRENT)
+        })
+        .map(|(version_id, _)| version_id.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_pending_version_ignores_current() {
+        let version_ids_to_stages = HashMap::from([
+            ("v1".to_string(), vec!["AWSPREVIOUS".to_string()]),
+            (
+                "v2".to_string(),
+                vec!["AWSCURRENT".to_string(), "AWSPENDING".to_string()],
+            ),
+        ]);
+        assert_eq!(find_pending_version(&version_ids_to_stages), None);
+
+        let version_ids_to_stages = HashMap::from([
+            ("v2".to_string(), vec!["AWSCURRENT".to_string()]),
+            ("v3".to_string(), vec!["AWSPENDING".to_string()]),
+        ]);
+        assert_eq!(
+            find_pending_version(&version_ids_to_stages),
+            Some("v3".to_string())
+        );
+    }
+}
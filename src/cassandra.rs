@@ -0,0 +1,150 @@
+// Copyright (c) 2024 PostFinance AG
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use log::{debug, trace};
+use scylla::client::session::Session;
+use scylla::client::session_builder::SessionBuilder;
+use tokio::runtime::{Builder, Runtime};
+
+use crate::config::{CassandraConfig, Config};
+use crate::database::DatabaseClient;
+
+pub(crate) struct CassandraClient {
+    cassandra_config: CassandraConfig,
+    rt: Runtime,
+}
+
+impl CassandraClient {
+    pub(crate) fn init(config: &Config) -> CassandraClient {
+        let cassandra_config = config
+            .cassandra
+            .clone()
+            .expect("Missing Cassandra configuration");
+
+        debug!(
+            "Connecting to Cassandra at: {}",
+            cassandra_config.contact_points.join(", ")
+        );
+
+        CassandraClient {
+            cassandra_config,
+            rt: Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("Failed to build Cassandra connection"),
+        }
+    }
+
+    fn connect_for_user(&self, username: &str, password: &str) -> Result<Session, String> {
+        self.rt
+            .block_on(
+                SessionBuilder::new()
+                    .known_nodes(&self.cassandra_config.contact_points)
+                    .user(username, password)
+                    .build(),
+            )
+            .map_err(|e| e.to_string())
+    }
+
+    fn get_login_timeout_seconds(&self) -> u64 {
+        match self.cassandra_config.login_timeout_seconds {
+            Some(seconds) => seconds as u64,
+            None => 60,
+        }
+    }
+
+    /// Role changes are stored in the `system_auth` keyspace, which is only eventually consistent across nodes.
+    fn wait_for_login(&self, username: &str, password: &str) {
+        let timeout_duration = Duration::from_secs(self.get_login_timeout_seconds());
+        let start_time = Instant::now();
+
+        loop {
+            match self.connect_for_user(username, password) {
+                Ok(_) => return,
+                Err(e) => {
+                    if start_time.elapsed() >= timeout_duration {
+                        panic!("Timeout reached while waiting for login of '{username}': {e}");
+                    }
+
+                    debug!("Login of '{username}' not possible yet: {e}");
+                }
+            }
+
+            sleep(Duration::from_secs(2));
+        }
+    }
+}
+
+impl DatabaseClient for CassandraClient {
+    fn update_password(&self, username: String, password: String, new_password: String) {
+        let session = self
+            .connect_for_user(&username, &password)
+            .unwrap_or_else(|e| panic!("Failed to build Cassandra connection: {e}"));
+
+        self.rt
+            .block_on(session.query_unpaged(alter_password_statement(&username, &new_password), ()))
+            .unwrap_or_else(|e| panic!("Failed to update password of '{username}': {e}"));
+
+        self.wait_for_login(&username, &new_password);
+
+        trace!("Updated password of '{username}' and verified login");
+    }
+
+    fn verify_password(&self, username: &str, password: &str) -> bool {
+        self.connect_for_user(username, password).is_ok()
+    }
+}
+
+fn alter_password_statement(username: &str, password: &str) -> String {
+    format!("ALTER ROLE {username} WITH PASSWORD = '{password}'")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::{ArgoConfig, VaultConfig};
+
+    #[test]
+    fn init() {
+        let config = create_config_with_cassandra();
+
+        let fixture = CassandraClient::init(&config);
+
+        assert_eq!(
+            fixture.cassandra_config.contact_points,
+            vec!["testhost:9042"]
+        );
+        assert_eq!(fixture.get_login_timeout_seconds(), 60);
+    }
+
+    #[test]
+    fn alter_password_statement_uses_cql() {
+        assert_eq!(
+            alter_password_statement("user1", "secret"),
+            "ALTER ROLE user1 WITH PASSWORD = 'secret'"
+        );
+    }
+
+    fn create_config_with_cassandra() -> Config {
+        Config {
+            argo_cd: ArgoConfig::default(),
+            aws_secrets_manager: None,
+            cassandra: Some(CassandraConfig {
+                contact_points: vec!["testhost:9042".to_string()],
+                login_timeout_seconds: None,
+            }),
+            clickhouse: None,
+            kubernetes: None,
+            lock: None,
+            plugin: None,
+            postgres: None,
+            vault: Some(VaultConfig::default()),
+        }
+    }
+}
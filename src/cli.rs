@@ -28,6 +28,16 @@ pub(crate) enum Command {
     ///
     /// This command orchestrates the process of generating new secrets, updating the database, and storing the new secrets in Vault.
     Rotate(RotateArgs),
+
+    /// List earlier versions of the Vault secret.
+    ///
+    /// This command shows when each version was written and which user was active, without revealing any passwords.
+    History(HistoryArgs),
+
+    /// Restore an earlier version of the Vault secret.
+    ///
+    /// This command is a break-glass tool for rotations gone wrong: it writes the users and passwords of an earlier version back to Vault.
+    Restore(RestoreArgs),
 }
 
 /// Base arguments for subcommands that share common parameters.
@@ -47,6 +57,37 @@ pub(crate) struct RotateArgs {
     /// The length of the randomly generated alphanumeric password
     #[clap(short, long, default_value = "20")]
     pub(crate) password_length: usize,
+
+    /// Take over the configured lock even if another run still holds it
+    #[clap(long)]
+    pub(crate) break_lock: bool,
+}
+
+/// Arguments specific to the `history` subcommand.
+#[derive(Parser, Debug)]
+pub(crate) struct HistoryArgs {
+    #[clap(flatten)] // Inherit arguments from BaseArgs
+    pub(crate) base: BaseArgs,
+}
+
+/// Arguments specific to the `restore` subcommand.
+#[derive(Parser, Debug)]
+#[command(disable_version_flag(true))] // `--version` selects the secret version to restore
+pub(crate) struct RestoreArgs {
+    #[clap(flatten)] // Inherit arguments from BaseArgs
+    pub(crate) base: BaseArgs,
+
+    /// The version of the Vault secret to restore, as listed by `history`
+    #[clap(long)]
+    pub(crate) version: u64,
+
+    /// Sync the ArgoCD application after restoring, so that it picks up the restored users and passwords
+    #[clap(long)]
+    pub(crate) sync: bool,
+
+    /// Take over the configured lock even if another run still holds it
+    #[clap(long)]
+    pub(crate) break_lock: bool,
 }
 
 /// Arguments specific to the `init-vault` subcommand.
@@ -0,0 +1,156 @@
+// Copyright (c) 2024 PostFinance AG
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+
+use log::{debug, trace};
+use reqwest::Client;
+use tokio::runtime::{Builder, Runtime};
+
+use crate::config::{ClickHouseConfig, Config};
+use crate::database::DatabaseClient;
+
+const CLICKHOUSE_USER_HEADER: &str = "X-ClickHouse-User";
+const CLICKHOUSE_KEY_HEADER: &str = "X-ClickHouse-Key";
+
+pub(crate) struct ClickHouseClient {
+    clickhouse_config: ClickHouseConfig,
+    client: Client,
+    rt: Runtime,
+}
+
+impl ClickHouseClient {
+    pub(crate) fn init(config: &Config) -> ClickHouseClient {
+        let clickhouse_config = config
+            .clickhouse
+            .clone()
+            .expect("Missing ClickHouse configuration");
+
+        debug!(
+            "Connecting to ClickHouse at: {}",
+            clickhouse_config.base_url
+        );
+
+        ClickHouseClient {
+            client: Self::get_clickhouse_client(&clickhouse_config),
+            clickhouse_config,
+            rt: Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("Failed to build ClickHouse connection"),
+        }
+    }
+
+    fn get_clickhouse_client(clickhouse_config: &ClickHouseConfig) -> Client {
+        match clickhouse_config.danger_accept_insecure {
+            Some(accept_insecure) => Client::builder()
+                .danger_accept_invalid_certs(accept_insecure)
+                .build()
+                .expect("Failed to build HTTP client"),
+            None => Client::new(),
+        }
+    }
+
+    fn execute(&self, username: &str, password: &str, query: String) -> Result<String, String> {
+        let request = self
+            .client
+            .post(self.clickhouse_config.base_url.as_str())
+            .header(CLICKHOUSE_USER_HEADER, username)
+            .header(CLICKHOUSE_KEY_HEADER, password)
+            .body(query)
+            .build()
+            .expect("Failed to build ClickHouse query request");
+
+        let response = self
+            .rt
+            .block_on(self.client.execute(request))
+            .map_err(|e| e.to_string())?;
+
+        let response_status = response.status();
+        let response_text = self
+            .rt
+            .block_on(response.text())
+            .map_err(|e| e.to_string())?;
+
+        if response_status.is_success() {
+            Ok(response_text)
+        } else {
+            Err(format!("{response_status}: {}", response_text.trim()))
+        }
+    }
+}
+
+impl DatabaseClient for ClickHouseClient {
+    fn update_password(&self, username: String, password: String, new_password: String) {
+        self.execute(
+            &username,
+            &password,
+            alter_password_statement(&username, &new_password),
+        )
+        .unwrap_or_else(|e| panic!("Failed to update password of '{username}': {e}"));
+
+        self.execute(&username, &new_password, "SELECT 1".to_string())
+            .unwrap_or_else(|e| panic!("Failed to verify login of '{username}': {e}"));
+
+        trace!("Updated password of '{username}' and verified login");
+    }
+
+    fn verify_password(&self, username: &str, password: &str) -> bool {
+        self.execute(username, password, "SELECT 1".to_string())
+            .is_ok()
+    }
+}
+
+fn alter_password_statement(username: &str, password: &str) -> String {
+    format!("ALTER USER {username} IDENTIFIED WITH sha256_password BY '{password}'")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::{ArgoConfig, VaultConfig};
+
+    #[test]
+    fn init() {
+        let config = create_config_with_clickhouse();
+
+        let fixture = ClickHouseClient::init(&config);
+
+        assert_eq!(fixture.clickhouse_config.base_url, "http://testhost:8123");
+    }
+
+    #[test]
+    #[should_panic(expected = "Missing ClickHouse configuration")]
+    fn init_missing_configuration() {
+        let mut config = create_config_with_clickhouse();
+        config.clickhouse = None;
+
+        ClickHouseClient::init(&config);
+    }
+
+    #[test]
+    fn alter_password_statement_uses_sha256() {
+        assert_eq!(
+            alter_password_statement("user1", "secret"),
+            "ALTER USER user1 IDENTIFIED WITH sha256_password BY 'secret'"
+        );
+    }
+
+    fn create_config_with_clickhouse() -> Config {
+        Config {
+            argo_cd: ArgoConfig::default(),
+            aws_secrets_manager: None,
+            cassandra: None,
+            clickhouse: Some(ClickHouseConfig {
+                base_url: "http://testhost:8123".to_string(),
+                danger_accept_insecure: None,
+            }),
+            kubernetes: None,
+            lock: None,
+            plugin: None,
+            postgres: None,
+            vault: Some(VaultConfig::default()),
+        }
+    }
+}
@@ -5,39 +5,253 @@
 
 use log::debug;
 use serde::Deserialize;
-use std::{fs::File, io::Read, path::PathBuf};
+use std::{collections::BTreeMap, fs::File, io::Read, path::PathBuf};
+
+const POLL_INTERVAL_SECONDS: u16 = 5;
+const MAX_POLL_INTERVAL_SECONDS: u16 = 60;
+const MAX_POLL_BACKOFF_FACTOR: f64 = 10.0;
 
 #[derive(Deserialize, Debug)]
 pub(crate) struct Config {
     pub(crate) argo_cd: ArgoConfig,
-    pub(crate) postgres: PostgresConfig,
-    pub(crate) vault: VaultConfig,
+    pub(crate) aws_secrets_manager: Option<AwsSecretsManagerConfig>,
+    pub(crate) cassandra: Option<CassandraConfig>,
+    pub(crate) clickhouse: Option<ClickHouseConfig>,
+    pub(crate) kubernetes: Option<KubernetesConfig>,
+    pub(crate) lock: Option<LockConfig>,
+    pub(crate) plugin: Option<PluginConfig>,
+    pub(crate) postgres: Option<PostgresConfig>,
+    pub(crate) vault: Option<VaultConfig>,
 }
 
 #[derive(Clone, Deserialize, Debug)]
 pub(crate) struct ArgoConfig {
-    pub(crate) application: String,
+    pub(crate) application: Option<String>,
+    pub(crate) applications: Option<Vec<String>>,
+    /// A label selector like `team=payments`, resolved to the matching applications when propeller starts.
+    pub(crate) selector: Option<String>,
+    pub(crate) sync_order: Option<ArgoSyncOrder>,
     pub(crate) base_url: String,
     pub(crate) danger_accept_insecure: Option<bool>,
     pub(crate) sync_timeout_seconds: Option<u16>,
+    /// Whether to re-render the manifests before syncing, e.g. for manifests sourced from Vault by a plugin.
+    pub(crate) hard_refresh: Option<bool>,
+    /// Applications rendering the secret through a Vault plugin, whose manifests must change after the hard refresh.
+    pub(crate) wait_for_manifest_change: Option<Vec<String>>,
+    /// Whether requests may be sent without credentials, e.g. to a local ArgoCD instance.
+    pub(crate) allow_unauthenticated: Option<bool>,
+    pub(crate) auth: Option<ArgoAuthConfig>,
+    pub(crate) operation_in_progress: Option<OperationInProgressPolicy>,
+    pub(crate) sync: Option<ArgoSyncConfig>,
+    pub(crate) rollout: Option<ArgoRolloutConfig>,
 }
 
 impl Default for ArgoConfig {
     fn default() -> Self {
         ArgoConfig {
-            application: String::from("propeller"),
+            application: Option::from(String::from("propeller")),
+            applications: None,
+            selector: None,
+            sync_order: Option::from(ArgoSyncOrder::Parallel),
             base_url: String::from("http://localhost:3100"),
             danger_accept_insecure: Option::from(false),
             sync_timeout_seconds: Option::from(60),
+            hard_refresh: Option::from(false),
+            wait_for_manifest_change: None,
+            allow_unauthenticated: Option::from(false),
+            auth: Option::from(ArgoAuthConfig::Token),
+            operation_in_progress: Option::from(OperationInProgressPolicy::Wait),
+            sync: None,
+            rollout: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ArgoSyncOrder {
+    /// Sync all applications, then wait for all of them.
+    #[default]
+    Parallel,
+    /// Sync one application after the other, each waiting for the rollout of the previous one.
+    Sequential,
+}
+
+/// What to do if ArgoCD refuses the sync because another operation, e.g. an auto-sync, is still running.
+#[derive(Clone, Copy, Default, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum OperationInProgressPolicy {
+    /// Wait for the running operation to finish, then sync again.
+    #[default]
+    Wait,
+    /// Terminate the running operation, then sync again.
+    Terminate,
+    Fail,
+}
+
+#[derive(Clone, Default, Deserialize, Debug)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub(crate) enum ArgoAuthConfig {
+    #[default]
+    Token,
+    TokenFile(TokenFileAuthConfig),
+    Vault(ArgoVaultAuthConfig),
+    Session(ArgoSessionAuthConfig),
+}
+
+/// How propeller waits for the rollout of an application after syncing it.
+#[derive(Clone, Default, Deserialize, Debug)]
+pub(crate) struct ArgoRolloutConfig {
+    pub(crate) success: Option<ArgoStatusConditionConfig>,
+    pub(crate) failure: Option<ArgoStatusConditionConfig>,
+    pub(crate) poll_interval_seconds: Option<u16>,
+    /// The poll interval is multiplied by this factor after every poll, up to `max_poll_interval_seconds`.
+    pub(crate) poll_backoff_factor: Option<f64>,
+    pub(crate) max_poll_interval_seconds: Option<u16>,
+}
+
+impl ArgoRolloutConfig {
+    pub(crate) fn get_poll_interval_seconds(&self) -> u16 {
+        self.poll_interval_seconds.unwrap_or(POLL_INTERVAL_SECONDS)
+    }
+
+    pub(crate) fn get_poll_backoff_factor(&self) -> f64 {
+        self.poll_backoff_factor.unwrap_or(1.0)
+    }
+
+    pub(crate) fn get_max_poll_interval_seconds(&self) -> u16 {
+        self.max_poll_interval_seconds
+            .unwrap_or(MAX_POLL_INTERVAL_SECONDS)
+    }
+}
+
+/// Accepted values of the application status. Unset fields match any value.
+#[derive(Clone, Default, Deserialize, Debug, PartialEq)]
+pub(crate) struct ArgoStatusConditionConfig {
+    pub(crate) sync_status: Option<Vec<String>>,
+    pub(crate) health_status: Option<Vec<String>>,
+    pub(crate) operation_phase: Option<Vec<String>>,
+}
+
+/// Options of the sync request, e.g. to only sync the Secret and the Deployment consuming it.
+#[derive(Clone, Default, Deserialize, Debug)]
+pub(crate) struct ArgoSyncConfig {
+    pub(crate) prune: Option<bool>,
+    pub(crate) revision: Option<String>,
+    pub(crate) strategy: Option<ArgoSyncStrategy>,
+    pub(crate) force: Option<bool>,
+    /// Options like `ApplyOutOfSyncOnly=true`, as in `spec.syncPolicy.syncOptions` of the application.
+    pub(crate) sync_options: Option<Vec<String>>,
+    pub(crate) resources: Option<Vec<ArgoSyncResourceConfig>>,
+}
+
+#[derive(Clone, Copy, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ArgoSyncStrategy {
+    Apply,
+    Hook,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub(crate) struct ArgoSyncResourceConfig {
+    pub(crate) group: Option<String>,
+    pub(crate) kind: String,
+    pub(crate) name: String,
+    pub(crate) namespace: Option<String>,
+}
+
+/// A token stored in Vault, read with the credentials of the `vault` configuration.
+#[derive(Clone, Deserialize, Debug)]
+pub(crate) struct ArgoVaultAuthConfig {
+    pub(crate) path: String,
+    pub(crate) mount: Option<String>,
+    pub(crate) key: Option<String>,
+}
+
+/// A login with the credentials of a local ArgoCD account.
+#[derive(Clone, Deserialize, Debug)]
+pub(crate) struct ArgoSessionAuthConfig {
+    pub(crate) username: String,
+    pub(crate) password_path: Option<PathBuf>,
+}
+
+/// An AWS Secrets Manager secret holding the users and passwords, as an alternative to Vault.
+#[derive(Clone, Deserialize, Debug)]
+pub(crate) struct AwsSecretsManagerConfig {
+    pub(crate) secret_id: String,
+    pub(crate) region: Option<String>,
+    pub(crate) endpoint: Option<String>,
+    pub(crate) keys: Option<VaultKeysConfig>,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub(crate) struct CassandraConfig {
+    pub(crate) contact_points: Vec<String>,
+    pub(crate) login_timeout_seconds: Option<u16>,
+}
+
+impl Default for CassandraConfig {
+    fn default() -> Self {
+        CassandraConfig {
+            contact_points: vec![String::from("localhost:9042")],
+            login_timeout_seconds: Option::from(60),
         }
     }
 }
 
+#[derive(Clone, Deserialize, Debug)]
+pub(crate) struct ClickHouseConfig {
+    pub(crate) base_url: String,
+    pub(crate) danger_accept_insecure: Option<bool>,
+}
+
+impl Default for ClickHouseConfig {
+    fn default() -> Self {
+        ClickHouseConfig {
+            base_url: String::from("http://localhost:8123"),
+            danger_accept_insecure: Option::from(false),
+        }
+    }
+}
+
+/// Exclusive lease taken for the whole rotation, so that overlapping runs can't interfere with each other.
+#[derive(Clone, Deserialize, Debug)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub(crate) enum LockConfig {
+    Vault(VaultLockConfig),
+    Postgres,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub(crate) struct VaultLockConfig {
+    pub(crate) path: Option<String>,
+    pub(crate) ttl_seconds: Option<u32>,
+}
+
+/// A Kubernetes Secret holding the users and passwords, as an alternative to Vault.
+#[derive(Clone, Deserialize, Debug)]
+pub(crate) struct KubernetesConfig {
+    pub(crate) secret: String,
+    pub(crate) namespace: Option<String>,
+    pub(crate) kubeconfig_path: Option<PathBuf>,
+    pub(crate) context: Option<String>,
+    pub(crate) keys: Option<VaultKeysConfig>,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub(crate) struct PluginConfig {
+    pub(crate) executable: PathBuf,
+    pub(crate) args: Option<Vec<String>>,
+    /// The time after which a plugin invocation is killed, as the rotation holds the lock while waiting for it.
+    pub(crate) timeout_seconds: Option<u16>,
+}
+
 #[derive(Clone, Deserialize, Debug)]
 pub(crate) struct PostgresConfig {
     pub(crate) host: String,
     pub(crate) port: u16,
     pub(crate) database: String,
+    pub(crate) dialect: Option<PostgresDialect>,
 }
 
 impl Default for PostgresConfig {
@@ -46,14 +260,34 @@ impl Default for PostgresConfig {
             host: String::from("localhost"),
             port: 5432,
             database: String::from("propeller"),
+            dialect: Option::from(PostgresDialect::PostgreSQL),
         }
     }
 }
 
+/// Database engines speaking the PostgreSQL wire protocol, each with its own password DDL and session views.
+#[derive(Clone, Copy, Default, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum PostgresDialect {
+    #[default]
+    PostgreSQL,
+    CockroachDB,
+    YugabyteDB,
+}
+
 #[derive(Clone, Deserialize, Debug)]
 pub(crate) struct VaultConfig {
     pub(crate) base_url: String,
     pub(crate) path: String,
+    pub(crate) mount: Option<String>,
+    pub(crate) kv_version: Option<u8>,
+    pub(crate) namespace: Option<String>,
+    pub(crate) tls: Option<VaultTlsConfig>,
+    pub(crate) keys: Option<VaultKeysConfig>,
+    /// Extra keys rendered from the connection settings and the active user, e.g. a JDBC URL.
+    pub(crate) templates: Option<BTreeMap<String, String>>,
+    pub(crate) fan_out: Option<Vec<VaultFanOutConfig>>,
+    pub(crate) auth: Option<VaultAuthConfig>,
 }
 
 impl Default for VaultConfig {
@@ -61,10 +295,104 @@ impl Default for VaultConfig {
         VaultConfig {
             base_url: String::from("http://localhost:8200"),
             path: String::from("propeller"),
+            mount: Option::from(String::from("secret")),
+            kv_version: Option::from(2),
+            namespace: None,
+            tls: None,
+            keys: Option::from(VaultKeysConfig::default()),
+            templates: None,
+            fan_out: None,
+            auth: Option::from(VaultAuthConfig::Token),
         }
     }
 }
 
+/// Keys of the Vault secret holding the users and passwords. Keys starting with `/` are JSON pointers into nested
+/// objects.
+#[derive(Clone, Deserialize, Debug)]
+pub(crate) struct VaultKeysConfig {
+    pub(crate) active_user: String,
+    pub(crate) active_user_password: String,
+    pub(crate) user_1: String,
+    pub(crate) user_1_password: String,
+    pub(crate) user_2: String,
+    pub(crate) user_2_password: String,
+}
+
+impl Default for VaultKeysConfig {
+    fn default() -> Self {
+        VaultKeysConfig {
+            active_user: String::from("postgresql_active_user"),
+            active_user_password: String::from("postgresql_active_user_password"),
+            user_1: String::from("postgresql_user_1"),
+            user_1_password: String::from("postgresql_user_1_password"),
+            user_2: String::from("postgresql_user_2"),
+            user_2_password: String::from("postgresql_user_2_password"),
+        }
+    }
+}
+
+/// An additional Vault path receiving the active user and password on every switch, e.g. of another team.
+#[derive(Clone, Deserialize, Debug)]
+pub(crate) struct VaultFanOutConfig {
+    pub(crate) path: String,
+    pub(crate) mount: Option<String>,
+    pub(crate) kv_version: Option<u8>,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub(crate) struct VaultTlsConfig {
+    pub(crate) ca_cert_path: Option<PathBuf>,
+    pub(crate) client_cert_path: Option<PathBuf>,
+    pub(crate) client_key_path: Option<PathBuf>,
+    pub(crate) server_name: Option<String>,
+}
+
+#[derive(Clone, Default, Deserialize, Debug)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub(crate) enum VaultAuthConfig {
+    #[default]
+    Token,
+    TokenFile(TokenFileAuthConfig),
+    Kubernetes(KubernetesAuthConfig),
+    AppRole(AppRoleAuthConfig),
+    Jwt(JwtAuthConfig),
+    Cert(CertAuthConfig),
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub(crate) struct TokenFileAuthConfig {
+    pub(crate) path: PathBuf,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub(crate) struct KubernetesAuthConfig {
+    pub(crate) role: String,
+    pub(crate) mount: Option<String>,
+    pub(crate) jwt_path: Option<PathBuf>,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub(crate) struct AppRoleAuthConfig {
+    pub(crate) mount: Option<String>,
+    pub(crate) role_id_path: Option<PathBuf>,
+    pub(crate) secret_id_path: Option<PathBuf>,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub(crate) struct JwtAuthConfig {
+    pub(crate) role: Option<String>,
+    pub(crate) mount: Option<String>,
+    pub(crate) jwt_path: Option<PathBuf>,
+    pub(crate) jwt_env: Option<String>,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub(crate) struct CertAuthConfig {
+    pub(crate) mount: Option<String>,
+    pub(crate) name: Option<String>,
+}
+
 pub(crate) fn read_config(config_path: PathBuf) -> Config {
     let path_string = config_path.clone().into_os_string().into_string().unwrap();
     debug!("Reading config at: {path_string}");
@@ -76,7 +404,147 @@ pub(crate) fn read_config(config_path: PathBuf) -> Config {
         .read_to_string(&mut config_data)
         .expect("Failed to read configuration file");
 
-    serde_yaml::from_str(&config_data).expect("Failed to parse configuration")
+    let config: Config = serde_yaml::from_str(&config_data).expect("Failed to parse configuration");
+    validate_argo_cd_applications(&config);
+    validate_database_target(&config);
+    validate_secret_store(&config);
+    validate_lock(&config);
+    validate_argo_cd_auth(&config);
+    validate_argo_cd_hard_refresh(&config);
+    validate_argo_cd_sync(&config);
+    validate_argo_cd_rollout(&config);
+
+    config
+}
+
+fn validate_database_target(config: &Config) {
+    let configured_targets = [
+        config.cassandra.is_some(),
+        config.clickhouse.is_some(),
+        config.plugin.is_some(),
+        config.postgres.is_some(),
+    ]
+    .into_iter()
+    .filter(|is_configured| *is_configured)
+    .count();
+
+    match configured_targets {
+        0 => panic!("Failed to parse configuration: missing database target, expected one of `cassandra`, `clickhouse`, `plugin`, `postgres`"),
+        1 => {}
+        _ => panic!("Failed to parse configuration: more than one database target configured"),
+    }
+}
+
+fn validate_argo_cd_applications(config: &Config) {
+    let configured_applications = [
+        config.argo_cd.application.is_some(),
+        config.argo_cd.applications.is_some(),
+        config.argo_cd.selector.is_some(),
+    ]
+    .into_iter()
+    .filter(|is_configured| *is_configured)
+    .count();
+
+    match configured_applications {
+        0 => panic!("Failed to parse configuration: missing ArgoCD application, expected one of `application`, `applications`, `selector`"),
+        1 => {}
+        _ => panic!("Failed to parse configuration: more than one of `application`, `applications`, `selector` configured"),
+    }
+
+    if config
+        .argo_cd
+        .applications
+        .as_ref()
+        .is_some_and(|applications| applications.is_empty())
+    {
+        panic!("Failed to parse configuration: `applications` must not be empty");
+    }
+}
+
+fn validate_secret_store(config: &Config) {
+    let configured_stores = [
+        config.aws_secrets_manager.is_some(),
+        config.kubernetes.is_some(),
+        config.vault.is_some(),
+    ]
+    .into_iter()
+    .filter(|is_configured| *is_configured)
+    .count();
+
+    match configured_stores {
+        0 => panic!("Failed to parse configuration: missing secret store, expected one of `aws_secrets_manager`, `kubernetes`, `vault`"),
+        1 => {}
+        _ => panic!("Failed to parse configuration: more than one secret store configured"),
+    }
+}
+
+fn validate_lock(config: &Config) {
+    match config.lock {
+        Some(LockConfig::Postgres) if config.postgres.is_none() => {
+            panic!("Failed to parse configuration: the `postgres` lock backend requires the `postgres` database target")
+        }
+        // CockroachDB has no session-level advisory locks
+        Some(LockConfig::Postgres)
+            if config
+                .postgres
+                .as_ref()
+                .and_then(|postgres| postgres.dialect)
+                == Some(PostgresDialect::CockroachDB) =>
+        {
+            panic!("Failed to parse configuration: the `postgres` lock backend does not support the `cockroachdb` dialect")
+        }
+        Some(LockConfig::Vault(_)) if config.vault.is_none() => {
+            panic!("Failed to parse configuration: the `vault` lock backend requires the `vault` secret store")
+        }
+        _ => {}
+    }
+}
+
+/// The `vault` section configures the Vault secret store, so features connecting to Vault on their own depend on it.
+fn validate_argo_cd_auth(config: &Config) {
+    if matches!(config.argo_cd.auth, Some(ArgoAuthConfig::Vault(_))) && config.vault.is_none() {
+        panic!("Failed to parse configuration: the `vault` ArgoCD auth method requires the `vault` secret store");
+    }
+}
+
+fn validate_argo_cd_hard_refresh(config: &Config) {
+    if config.argo_cd.wait_for_manifest_change.is_some()
+        && !config.argo_cd.hard_refresh.unwrap_or(false)
+    {
+        panic!("Failed to parse configuration: `argo_cd.wait_for_manifest_change` requires `argo_cd.hard_refresh`");
+    }
+}
+
+fn validate_argo_cd_sync(config: &Config) {
+    let Some(sync) = &config.argo_cd.sync else {
+        return;
+    };
+
+    // ArgoCD only knows `force` as part of a strategy
+    if sync.force.is_some() && sync.strategy.is_none() {
+        panic!(
+            "Failed to parse configuration: `argo_cd.sync.force` requires `argo_cd.sync.strategy`"
+        );
+    }
+}
+
+fn validate_argo_cd_rollout(config: &Config) {
+    let Some(rollout) = &config.argo_cd.rollout else {
+        return;
+    };
+
+    if rollout.get_poll_interval_seconds() == 0 {
+        panic!("Failed to parse configuration: `argo_cd.rollout.poll_interval_seconds` must be greater than 0");
+    }
+
+    // Also rejects NaN and infinity, on which growing the poll interval would panic
+    if !(1.0..=MAX_POLL_BACKOFF_FACTOR).contains(&rollout.get_poll_backoff_factor()) {
+        panic!("Failed to parse configuration: `argo_cd.rollout.poll_backoff_factor` must be between 1 and {MAX_POLL_BACKOFF_FACTOR}");
+    }
+
+    if rollout.get_max_poll_interval_seconds() < rollout.get_poll_interval_seconds() {
+        panic!("Failed to parse configuration: `argo_cd.rollout.max_poll_interval_seconds` must not be less than `poll_interval_seconds`");
+    }
 }
 
 #[cfg(test)]
@@ -98,7 +566,9 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Failed to parse configuration: Error(\"missing field `postgres`")]
+    #[should_panic(
+        expected = "Failed to parse configuration: missing database target, expected one of `cassandra`, `clickhouse`, `plugin`, `postgres`"
+    )]
     fn read_config_missing_postgresql() {
         read_config(PathBuf::from(
             "tests/resources/config/missing_postgresql.yml",
@@ -106,8 +576,486 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "Failed to parse configuration: Error(\"missing field `vault`")]
+    #[should_panic(
+        expected = "Failed to parse configuration: missing secret store, expected one of `aws_secrets_manager`, `kubernetes`, `vault`"
+    )]
     fn read_config_missing_vault() {
         read_config(PathBuf::from("tests/resources/config/missing_vault.yml"));
     }
+
+    #[test]
+    fn read_config_postgres_dialect() {
+        let config = read_config(PathBuf::from("tests/resources/config/postgres_dialect.yml"));
+
+        assert_eq!(
+            config.postgres.unwrap().dialect,
+            Some(PostgresDialect::CockroachDB)
+        );
+    }
+
+    #[test]
+    fn read_config_cassandra() {
+        let config = read_config(PathBuf::from("tests/resources/config/cassandra.yml"));
+
+        let cassandra_config = config.cassandra.unwrap();
+        assert_eq!(
+            cassandra_config.contact_points,
+            vec!["localhost:9042", "localhost:9043"]
+        );
+        assert_eq!(cassandra_config.login_timeout_seconds, Some(30));
+    }
+
+    #[test]
+    fn read_config_clickhouse() {
+        let config = read_config(PathBuf::from("tests/resources/config/clickhouse.yml"));
+
+        assert!(config.postgres.is_none());
+        assert_eq!(config.clickhouse.unwrap().base_url, "http://localhost:8123");
+    }
+
+    #[test]
+    fn read_config_aws_secrets_manager() {
+        let config = read_config(PathBuf::from(
+            "tests/resources/config/aws_secrets_manager.yml",
+        ));
+
+        assert!(config.vault.is_none());
+
+        let aws_config = config.aws_secrets_manager.unwrap();
+        assert_eq!(aws_config.secret_id, "prod/propeller/database");
+        assert_eq!(aws_config.region, Some("eu-central-2".to_string()));
+        assert_eq!(
+            aws_config.endpoint,
+            Some("http://localhost:4566".to_string())
+        );
+        assert!(aws_config.keys.is_none());
+    }
+
+    #[test]
+    fn read_config_kubernetes() {
+        let config = read_config(PathBuf::from("tests/resources/config/kubernetes.yml"));
+
+        assert!(config.vault.is_none());
+
+        let kubernetes_config = config.kubernetes.unwrap();
+        assert_eq!(kubernetes_config.secret, "propeller-database");
+        assert_eq!(kubernetes_config.namespace, Some("demo".to_string()));
+        assert_eq!(
+            kubernetes_config.kubeconfig_path,
+            Some(PathBuf::from("/etc/propeller/kubeconfig"))
+        );
+        assert_eq!(kubernetes_config.context, None);
+        assert!(kubernetes_config.keys.is_none());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to parse configuration: more than one secret store configured"
+    )]
+    fn read_config_multiple_secret_stores() {
+        read_config(PathBuf::from(
+            "tests/resources/config/multiple_secret_stores.yml",
+        ));
+    }
+
+    #[test]
+    fn read_config_vault_kv_settings() {
+        let config = read_config(PathBuf::from(
+            "tests/resources/config/vault_kv_settings.yml",
+        ));
+
+        let vault_config = config.vault.unwrap();
+
+        assert_eq!(vault_config.mount, Some("kv-team-a".to_string()));
+        assert_eq!(vault_config.kv_version, Some(1));
+        assert_eq!(vault_config.namespace, Some("team-a".to_string()));
+    }
+
+    #[test]
+    fn read_config_vault_keys() {
+        let config = read_config(PathBuf::from("tests/resources/config/vault_keys.yml"));
+
+        let keys = config.vault.unwrap().keys.unwrap();
+        assert_eq!(keys.active_user, "DB_USER");
+        assert_eq!(keys.active_user_password, "DB_PASSWORD");
+        assert_eq!(keys.user_1, "/slots/blue/user");
+        assert_eq!(keys.user_2_password, "/slots/green/password");
+    }
+
+    #[test]
+    fn read_config_vault_templates() {
+        let config = read_config(PathBuf::from("tests/resources/config/vault_templates.yml"));
+
+        let templates = config.vault.unwrap().templates.unwrap();
+        assert_eq!(templates.len(), 2);
+        assert_eq!(
+            templates["jdbc_url"],
+            "jdbc:postgresql://{host}:{port}/{database}"
+        );
+        assert_eq!(
+            templates["/connection/url"],
+            "postgres://{username}:{password}@{host}:{port}/{database}"
+        );
+    }
+
+    #[test]
+    fn read_config_vault_fan_out() {
+        let config = read_config(PathBuf::from("tests/resources/config/vault_fan_out.yml"));
+
+        let fan_out = config.vault.unwrap().fan_out.unwrap();
+        assert_eq!(fan_out.len(), 2);
+        assert_eq!(fan_out[0].path, "team-a/database");
+        assert_eq!(fan_out[0].mount, None);
+        assert_eq!(fan_out[0].kv_version, None);
+        assert_eq!(fan_out[1].path, "database");
+        assert_eq!(fan_out[1].mount, Some("team-b".to_string()));
+        assert_eq!(fan_out[1].kv_version, Some(1));
+    }
+
+    #[test]
+    fn read_config_argo_cd_session_auth() {
+        let config = read_config(PathBuf::from(
+            "tests/resources/config/argo_cd_session_auth.yml",
+        ));
+
+        assert_eq!(config.argo_cd.allow_unauthenticated, None);
+        match config.argo_cd.auth {
+            Some(ArgoAuthConfig::Session(session)) => {
+                assert_eq!(session.username, "propeller");
+                assert_eq!(
+                    session.password_path,
+                    Some(PathBuf::from("/var/run/secrets/argocd/password"))
+                );
+            }
+            auth => panic!("Unexpected ArgoCD auth configuration: {auth:?}"),
+        }
+    }
+
+    #[test]
+    fn read_config_argo_cd_operation_in_progress() {
+        let config = read_config(PathBuf::from(
+            "tests/resources/config/argo_cd_operation_in_progress.yml",
+        ));
+
+        assert_eq!(
+            config.argo_cd.operation_in_progress,
+            Some(OperationInProgressPolicy::Terminate)
+        );
+    }
+
+    #[test]
+    fn read_config_argo_cd_applications() {
+        let config = read_config(PathBuf::from(
+            "tests/resources/config/argo_cd_applications.yml",
+        ));
+
+        assert_eq!(config.argo_cd.application, None);
+        assert_eq!(
+            config.argo_cd.applications,
+            Some(vec![
+                "demo-api".to_string(),
+                "demo-worker".to_string(),
+                "demo-cron".to_string()
+            ])
+        );
+        assert_eq!(config.argo_cd.sync_order, Some(ArgoSyncOrder::Sequential));
+    }
+
+    #[test]
+    fn read_config_argo_cd_rollout() {
+        let config = read_config(PathBuf::from("tests/resources/config/argo_cd_rollout.yml"));
+
+        let rollout = config.argo_cd.rollout.unwrap();
+        assert_eq!(
+            rollout.success,
+            Some(ArgoStatusConditionConfig {
+                sync_status: Some(vec!["Synced".to_string()]),
+                health_status: Some(vec!["Healthy".to_string(), "Suspended".to_string()]),
+                operation_phase: None,
+            })
+        );
+        assert_eq!(
+            rollout.failure,
+            Some(ArgoStatusConditionConfig {
+                sync_status: None,
+                health_status: None,
+                operation_phase: Some(vec!["Failed".to_string(), "Error".to_string()]),
+            })
+        );
+        assert_eq!(rollout.poll_interval_seconds, Some(2));
+        assert_eq!(rollout.poll_backoff_factor, Some(1.5));
+        assert_eq!(rollout.max_poll_interval_seconds, Some(30));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to parse configuration: `argo_cd.rollout.poll_interval_seconds` must be greater than 0"
+    )]
+    fn read_config_argo_cd_rollout_zero_poll_interval() {
+        read_config(PathBuf::from(
+            "tests/resources/config/argo_cd_rollout_zero_poll_interval.yml",
+        ));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to parse configuration: `argo_cd.rollout.poll_backoff_factor` must be between 1 and 10"
+    )]
+    fn read_config_argo_cd_rollout_invalid_backoff_factor() {
+        read_config(PathBuf::from(
+            "tests/resources/config/argo_cd_rollout_invalid_backoff_factor.yml",
+        ));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to parse configuration: `argo_cd.rollout.max_poll_interval_seconds` must not be less than `poll_interval_seconds`"
+    )]
+    fn read_config_argo_cd_rollout_max_poll_interval_too_short() {
+        read_config(PathBuf::from(
+            "tests/resources/config/argo_cd_rollout_max_poll_interval_too_short.yml",
+        ));
+    }
+
+    #[test]
+    fn read_config_argo_cd_selector() {
+        let config = read_config(PathBuf::from("tests/resources/config/argo_cd_selector.yml"));
+
+        assert_eq!(
+            config.argo_cd.selector,
+            Some("propeller.postfinance.ch/database=demo".to_string())
+        );
+        assert_eq!(config.argo_cd.sync_order, None);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to parse configuration: more than one of `application`, `applications`, `selector` configured"
+    )]
+    fn read_config_argo_cd_application_and_selector() {
+        read_config(PathBuf::from(
+            "tests/resources/config/argo_cd_application_and_selector.yml",
+        ));
+    }
+
+    #[test]
+    fn read_config_argo_cd_sync() {
+        let config = read_config(PathBuf::from("tests/resources/config/argo_cd_sync.yml"));
+
+        assert_eq!(config.argo_cd.hard_refresh, Some(true));
+        assert_eq!(
+            config.argo_cd.wait_for_manifest_change,
+            Some(vec!["propeller".to_string()])
+        );
+
+        let sync = config.argo_cd.sync.unwrap();
+        assert_eq!(sync.prune, Some(true));
+        assert_eq!(sync.revision, Some("main".to_string()));
+        assert_eq!(sync.strategy, Some(ArgoSyncStrategy::Apply));
+        assert_eq!(
+            sync.sync_options,
+            Some(vec!["ApplyOutOfSyncOnly=true".to_string()])
+        );
+
+        let resources = sync.resources.unwrap();
+        assert_eq!(resources.len(), 2);
+        assert_eq!(resources[0].group, None);
+        assert_eq!(resources[0].kind, "Secret");
+        assert_eq!(resources[0].name, "propeller-database");
+        assert_eq!(resources[1].group, Some("apps".to_string()));
+        assert_eq!(resources[1].namespace, Some("demo".to_string()));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to parse configuration: `argo_cd.sync.force` requires `argo_cd.sync.strategy`"
+    )]
+    fn read_config_argo_cd_sync_force_without_strategy() {
+        read_config(PathBuf::from(
+            "tests/resources/config/argo_cd_sync_force_without_strategy.yml",
+        ));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to parse configuration: `argo_cd.wait_for_manifest_change` requires `argo_cd.hard_refresh`"
+    )]
+    fn read_config_argo_cd_wait_for_manifest_change_without_hard_refresh() {
+        read_config(PathBuf::from(
+            "tests/resources/config/argo_cd_wait_for_manifest_change_without_hard_refresh.yml",
+        ));
+    }
+
+    #[test]
+    fn read_config_argo_cd_vault_auth() {
+        let config = read_config(PathBuf::from(
+            "tests/resources/config/argo_cd_vault_auth.yml",
+        ));
+
+        match config.argo_cd.auth {
+            Some(ArgoAuthConfig::Vault(vault)) => {
+                assert_eq!(vault.path, "argocd/propeller");
+                assert_eq!(vault.mount, None);
+                assert_eq!(vault.key, Some("api_token".to_string()));
+            }
+            auth => panic!("Unexpected ArgoCD auth configuration: {auth:?}"),
+        }
+    }
+
+    #[test]
+    fn read_config_vault_kubernetes_auth() {
+        let config = read_config(PathBuf::from(
+            "tests/resources/config/vault_kubernetes_auth.yml",
+        ));
+
+        match config.vault.unwrap().auth {
+            Some(VaultAuthConfig::Kubernetes(kubernetes)) => {
+                assert_eq!(kubernetes.role, "propeller");
+                assert_eq!(kubernetes.mount, Some("k8s-prod".to_string()));
+                assert_eq!(kubernetes.jwt_path, None);
+            }
+            auth => panic!("Unexpected Vault auth configuration: {auth:?}"),
+        }
+    }
+
+    #[test]
+    fn read_config_vault_app_role_auth() {
+        let config = read_config(PathBuf::from(
+            "tests/resources/config/vault_app_role_auth.yml",
+        ));
+
+        match config.vault.unwrap().auth {
+            Some(VaultAuthConfig::AppRole(app_role)) => {
+                assert_eq!(app_role.mount, None);
+                assert_eq!(app_role.role_id_path, None);
+                assert_eq!(
+                    app_role.secret_id_path,
+                    Some(PathBuf::from("/run/secrets/vault-secret-id"))
+                );
+            }
+            auth => panic!("Unexpected Vault auth configuration: {auth:?}"),
+        }
+    }
+
+    #[test]
+    fn read_config_vault_jwt_auth() {
+        let config = read_config(PathBuf::from("tests/resources/config/vault_jwt_auth.yml"));
+
+        match config.vault.unwrap().auth {
+            Some(VaultAuthConfig::Jwt(jwt)) => {
+                assert_eq!(jwt.role, Some("propeller-ci".to_string()));
+                assert_eq!(jwt.mount, Some("gitlab".to_string()));
+                assert_eq!(jwt.jwt_env, Some("VAULT_ID_TOKEN".to_string()));
+            }
+            auth => panic!("Unexpected Vault auth configuration: {auth:?}"),
+        }
+    }
+
+    #[test]
+    fn read_config_vault_cert_auth() {
+        let config = read_config(PathBuf::from("tests/resources/config/vault_cert_auth.yml"));
+
+        let vault_config = config.vault.unwrap();
+        let tls_config = vault_config.tls.unwrap();
+        assert_eq!(
+            tls_config.ca_cert_path,
+            Some(PathBuf::from("/etc/propeller/ca.pem"))
+        );
+        assert_eq!(
+            tls_config.client_cert_path,
+            Some(PathBuf::from("/etc/propeller/client.pem"))
+        );
+        assert_eq!(
+            tls_config.client_key_path,
+            Some(PathBuf::from("/etc/propeller/client-key.pem"))
+        );
+        assert_eq!(tls_config.server_name, Some("vault.internal".to_string()));
+
+        match vault_config.auth {
+            Some(VaultAuthConfig::Cert(cert)) => {
+                assert_eq!(cert.mount, None);
+                assert_eq!(cert.name, Some("propeller".to_string()));
+            }
+            auth => panic!("Unexpected Vault auth configuration: {auth:?}"),
+        }
+    }
+
+    #[test]
+    fn read_config_lock() {
+        let config = read_config(PathBuf::from("tests/resources/config/lock.yml"));
+
+        match config.lock {
+            Some(LockConfig::Vault(vault_lock)) => {
+                assert_eq!(vault_lock.path, Some("locks/propeller".to_string()));
+                assert_eq!(vault_lock.ttl_seconds, Some(600));
+            }
+            lock => panic!("Unexpected lock configuration: {lock:?}"),
+        }
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to parse configuration: the `postgres` lock backend requires the `postgres` database target"
+    )]
+    fn read_config_postgres_lock_without_postgres() {
+        read_config(PathBuf::from(
+            "tests/resources/config/postgres_lock_without_postgres.yml",
+        ));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to parse configuration: the `postgres` lock backend does not support the `cockroachdb` dialect"
+    )]
+    fn read_config_postgres_lock_cockroachdb() {
+        read_config(PathBuf::from(
+            "tests/resources/config/postgres_lock_cockroachdb.yml",
+        ));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to parse configuration: the `vault` lock backend requires the `vault` secret store"
+    )]
+    fn read_config_vault_lock_without_vault() {
+        read_config(PathBuf::from(
+            "tests/resources/config/vault_lock_without_vault.yml",
+        ));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to parse configuration: the `vault` ArgoCD auth method requires the `vault` secret store"
+    )]
+    fn read_config_argo_cd_vault_auth_without_vault() {
+        read_config(PathBuf::from(
+            "tests/resources/config/argo_cd_vault_auth_without_vault.yml",
+        ));
+    }
+
+    #[test]
+    fn read_config_plugin() {
+        let config = read_config(PathBuf::from("tests/resources/config/plugin.yml"));
+
+        let plugin_config = config.plugin.unwrap();
+        assert_eq!(
+            plugin_config.executable,
+            PathBuf::from("/usr/local/bin/propeller-ldap")
+        );
+        assert_eq!(
+            plugin_config.args,
+            Some(vec!["--realm".to_string(), "internal".to_string()])
+        );
+        assert_eq!(plugin_config.timeout_seconds, Some(10));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to parse configuration: more than one database target configured"
+    )]
+    fn read_config_multiple_database_targets() {
+        read_config(PathBuf::from(
+            "tests/resources/config/multiple_database_targets.yml",
+        ));
+    }
 }
@@ -3,11 +3,38 @@
 // This software is released under the MIT License.
 // https://opensource.org/licenses/MIT
 
-use crate::config::{Config, PostgresConfig};
+use crate::cassandra::CassandraClient;
+use crate::clickhouse::ClickHouseClient;
+use crate::config::{Config, PostgresConfig, PostgresDialect};
+use crate::plugin::PluginClient;
+use log::{trace, warn};
 use postgres::Error;
 use postgres::{Client, NoTls};
 use std::sync::Arc;
 
+const APPLICATION_NAME: &str = "propeller";
+const LOCK_APPLICATION_NAME: &str = "propeller-lock";
+
+/// A database whose users take turns being active, so that the passive one can safely change its password.
+pub(crate) trait DatabaseClient {
+    fn update_password(&self, username: String, password: String, new_password: String);
+
+    /// Whether the user can log in with the given password.
+    fn verify_password(&self, username: &str, password: &str) -> bool;
+}
+
+pub(crate) fn init_database_client(config: &Config) -> Box<dyn DatabaseClient> {
+    if config.cassandra.is_some() {
+        Box::new(CassandraClient::init(config))
+    } else if config.clickhouse.is_some() {
+        Box::new(ClickHouseClient::init(config))
+    } else if config.plugin.is_some() {
+        Box::new(PluginClient::init(config))
+    } else {
+        Box::new(PostgresClient::init(config))
+    }
+}
+
 pub trait ClientFactory {
     fn create_client(&self, connection_string: &str) -> Result<Client, Error>;
 }
@@ -28,23 +55,41 @@ pub struct PostgresClient {
 impl PostgresClient {
     pub(crate) fn init(config: &Config) -> PostgresClient {
         PostgresClient {
-            postgres_config: config.postgres.clone(),
+            postgres_config: config
+                .postgres
+                .clone()
+                .expect("Missing PostgreSQL configuration"),
             client_factory: Arc::new(PropellerClientFactory),
         }
     }
 
     pub(crate) fn connect_for_user(&self, username: String, password: String) -> Client {
+        self.connect(username, password, APPLICATION_NAME)
+    }
+
+    /// Connects for the PostgreSQL lock, whose session is not counted as an open session of the user.
+    pub(crate) fn connect_for_lock(&self, username: String, password: String) -> Client {
+        self.connect(username, password, LOCK_APPLICATION_NAME)
+    }
+
+    fn connect(&self, username: String, password: String, application_name: &str) -> Client {
+        self.client_factory
+            .create_client(&self.connection_string(&username, &password, application_name))
+            .expect("Failed to build PostgreSQL connection")
+    }
+
+    fn connection_string(&self, username: &str, password: &str, application_name: &str) -> String {
         let host = self.postgres_config.host.as_str();
         let port = self.postgres_config.port;
         let database = self.postgres_config.database.as_str();
 
-        let connection_string = format!(
-            "host={host} port={port} dbname={database} user={username} password={password}"
-        );
+        format!(
+            "host={host} port={port} dbname={database} user={username} password={password} application_name={application_name}"
+        )
+    }
 
-        self.client_factory
-            .create_client(&connection_string)
-            .expect("Failed to build PostgreSQL connection")
+    fn get_dialect(&self) -> PostgresDialect {
+        self.postgres_config.dialect.unwrap_or_default()
     }
 
     #[cfg(test)]
@@ -53,12 +98,80 @@ impl PostgresClient {
         client_factory: Arc<dyn ClientFactory>,
     ) -> PostgresClient {
         PostgresClient {
-            postgres_config: config.postgres.clone(),
+            postgres_config: config
+                .postgres
+                .clone()
+                .expect("Missing PostgreSQL configuration"),
             client_factory,
         }
     }
 }
 
+impl DatabaseClient for PostgresClient {
+    fn update_password(&self, username: String, password: String, new_password: String) {
+        let dialect = self.get_dialect();
+        let mut client = self.connect_for_user(username.clone(), password);
+
+        let open_sessions: i64 = client
+            .query_one(active_sessions_query(dialect).as_str(), &[])
+            .map(|row| row.get(0))
+            .unwrap_or_else(|e| panic!("Failed to query open sessions of '{username}': {e}"));
+        if open_sessions > 0 {
+            warn!("User '{username}' still has {open_sessions} open session(s) while changing its password");
+        }
+
+        if let Some(statement) = password_encryption_statement(dialect) {
+            client.batch_execute(statement).unwrap_or_else(|e| {
+                panic!("Failed to configure password encryption for '{username}': {e}")
+            });
+        }
+
+        client
+            .execute(
+                alter_password_statement(dialect, &username, &new_password).as_str(),
+                &[],
+            )
+            .unwrap_or_else(|e| panic!("Failed to update password of '{username}': {e}"));
+
+        trace!("Updated password of '{username}' using {dialect:?} dialect");
+    }
+
+    fn verify_password(&self, username: &str, password: &str) -> bool {
+        self.client_factory
+            .create_client(&self.connection_string(username, password, APPLICATION_NAME))
+            .is_ok()
+    }
+}
+
+fn alter_password_statement(dialect: PostgresDialect, username: &str, password: &str) -> String {
+    match dialect {
+        PostgresDialect::PostgreSQL | PostgresDialect::YugabyteDB => {
+            format!("ALTER ROLE {username} WITH PASSWORD '{password}'")
+        }
+        PostgresDialect::CockroachDB => format!("ALTER USER {username} WITH PASSWORD '{password}'"),
+    }
+}
+
+/// Counts the sessions of the connected user, excluding the ones used by propeller itself.
+fn active_sessions_query(dialect: PostgresDialect) -> String {
+    match dialect {
+        PostgresDialect::PostgreSQL | PostgresDialect::YugabyteDB => format!(
+            "SELECT count(*) FROM pg_stat_activity WHERE usename = current_user AND pid <> pg_backend_pid() AND application_name <> '{LOCK_APPLICATION_NAME}'"
+        ),
+        PostgresDialect::CockroachDB => format!(
+            "SELECT count(*) - 1 FROM crdb_internal.cluster_sessions WHERE user_name = current_user AND application_name <> '{LOCK_APPLICATION_NAME}'"
+        ),
+    }
+}
+
+/// YugabyteDB may still default to MD5 hashing, CockroachDB only allows changing it cluster-wide.
+fn password_encryption_statement(dialect: PostgresDialect) -> Option<&'static str> {
+    match dialect {
+        PostgresDialect::YugabyteDB => Some("SET password_encryption = 'scram-sha-256'"),
+        PostgresDialect::PostgreSQL | PostgresDialect::CockroachDB => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -73,6 +186,7 @@ mod tests {
             assert!(connection_string.contains("dbname=testdb"));
             assert!(connection_string.contains("user=testuser"));
             assert!(connection_string.contains("password=testpass"));
+            assert!(connection_string.contains("application_name=propeller"));
 
             Client::connect("", NoTls)
         }
@@ -87,6 +201,49 @@ mod tests {
         assert_eq!(fixture.postgres_config.host, "testhost");
         assert_eq!(fixture.postgres_config.port, 2345);
         assert_eq!(fixture.postgres_config.database, "testdb");
+        assert_eq!(fixture.get_dialect(), PostgresDialect::PostgreSQL);
+    }
+
+    #[test]
+    fn alter_password_statement_per_dialect() {
+        assert_eq!(
+            alter_password_statement(PostgresDialect::PostgreSQL, "user1", "secret"),
+            "ALTER ROLE user1 WITH PASSWORD 'secret'"
+        );
+        assert_eq!(
+            alter_password_statement(PostgresDialect::YugabyteDB, "user1", "secret"),
+            "ALTER ROLE user1 WITH PASSWORD 'secret'"
+        );
+        assert_eq!(
+            alter_password_statement(PostgresDialect::CockroachDB, "user1", "secret"),
+            "ALTER USER user1 WITH PASSWORD 'secret'"
+        );
+    }
+
+    #[test]
+    fn active_sessions_query_per_dialect() {
+        assert!(active_sessions_query(PostgresDialect::PostgreSQL).contains("pg_stat_activity"));
+        assert!(active_sessions_query(PostgresDialect::YugabyteDB).contains("pg_stat_activity"));
+        assert!(active_sessions_query(PostgresDialect::CockroachDB)
+            .contains("crdb_internal.cluster_sessions"));
+        assert!(active_sessions_query(PostgresDialect::PostgreSQL)
+            .contains(&format!("application_name <> '{LOCK_APPLICATION_NAME}'")));
+    }
+
+    #[test]
+    fn password_encryption_statement_per_dialect() {
+        assert_eq!(
+            password_encryption_statement(PostgresDialect::PostgreSQL),
+            None
+        );
+        assert_eq!(
+            password_encryption_statement(PostgresDialect::CockroachDB),
+            None
+        );
+        assert_eq!(
+            password_encryption_statement(PostgresDialect::YugabyteDB),
+            Some("SET password_encryption = 'scram-sha-256'")
+        );
     }
 
     #[test]
@@ -106,12 +263,19 @@ mod tests {
     fn create_config_with_testdb() -> Config {
         Config {
             argo_cd: ArgoConfig::default(),
-            postgres: PostgresConfig {
+            aws_secrets_manager: None,
+            cassandra: None,
+            clickhouse: None,
+            kubernetes: None,
+            lock: None,
+            plugin: None,
+            postgres: Some(PostgresConfig {
                 host: "testhost".to_string(),
                 port: 2345,
                 database: "testdb".to_string(),
-            },
-            vault: VaultConfig::default(),
+                dialect: None,
+            }),
+            vault: Some(VaultConfig::default()),
         }
     }
 }
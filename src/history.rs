@@ -0,0 +1,148 @@
+// Copyright (c) 2024 PostFinance AG
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+
+use log::info;
+
+use crate::argo_cd::ArgoCD;
+use crate::cli::RestoreArgs;
+use crate::config::Config;
+use crate::lock::{acquire_lock, RotationLock};
+use crate::secret_store::SecretStore;
+use crate::vault::{SecretVersion, Vault};
+
+pub(crate) fn print_secret_history(vault: &mut Vault) {
+    let versions = vault
+        .read_secret_history()
+        .unwrap_or_else(|e| panic!("Failed to read secret history: {e}"));
+
+    print!("{}", format_history(&versions));
+}
+
+pub(crate) fn restore_secret_version(
+    restore_args: &RestoreArgs,
+    config: &Config,
+    vault: &mut Vault,
+) {
+    // Missing ArgoCD credentials must fail before the restore
+    let mut argo_cd = restore_args.sync.then(|| ArgoCD::init(config));
+
+    let mut lock: Option<Box<dyn RotationLock>> =
+        acquire_lock(config, vault, restore_args.break_lock);
+
+    let vault_structure = vault
+        .read_secret_version(restore_args.version)
+        .unwrap_or_else(|e| {
+            panic!(
+                "Failed to read version {} of path '{}': {e}",
+                restore_args.version,
+                vault.location()
+            )
+        });
+
+    if let Some(argo_cd) = argo_cd.as_ref() {
+        vault.set_argo_cd_applications(argo_cd.applications());
+    }
+
+    if let Some(lock) = lock.as_mut() {
+        lock.hold_on_failure();
+    }
+
+    vault
+        .write_secret(&vault_structure)
+        .unwrap_or_else(|e| panic!("Failed to restore version {}: {e}", restore_args.version));
+    vault
+        .fan_out_secret(&vault_structure)
+        .unwrap_or_else(|e| panic!("{e}"));
+
+    println!(
+        "Successfully restored version {} of Vault path '{}'",
+        restore_args.version,
+        vault.location()
+    );
+
+    if let Some(argo_cd) = argo_cd.as_mut() {
+        info!("Starting ArgoCD rollout of the restored secret");
+
+        argo_cd.rollout();
+    }
+
+    if let Some(lock) = lock.as_mut() {
+        lock.release();
+    }
+}
+
+/// Lists the versions as table. Passwords are never shown, only which users were stored.
+fn format_history(versions: &[SecretVersion]) -> String {
+    let header = format!(
+        "{:<8} {:<32} {:<12} {:<20} {:<20} {:<20}",
+        "VERSION", "CREATED", "ACTIVE SLOT", "ACTIVE USER", "USER 1", "USER 2"
+    );
+    let mut history = format!("{}\n", header.trim_end());
+
+    for version in versions {
+        let line = match &version.vault_structure {
+            Some(vault_structure) => format!(
+                "{:<8} {:<32} {:<12} {:<20} {:<20} {:<20}",
+                version.version,
+                version.created_time,
+                vault_structure.active_slot().unwrap_or("-"),
+                vault_structure.postgresql_active_user,
+                vault_structure.postgresql_user_1,
+                vault_structure.postgresql_user_2
+            ),
+            None => format!(
+                "{:<8} {:<32} {}",
+                version.version, version.created_time, "(deleted or unreadable)"
+            ),
+        };
+
+        history.push_str(line.trim_end());
+        history.push('\n');
+    }
+
+    history
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::vault::VaultStructure;
+
+    #[test]
+    fn format_history_redacts_passwords() {
+        let versions = vec![
+            SecretVersion {
+                version: 2,
+                created_time: "2024-05-02T10:00:00.000000Z".to_string(),
+                vault_structure: Some(VaultStructure {
+                    postgresql_active_user: "user2".to_string(),
+                    postgresql_active_user_password: "secret2".to_string(),
+                    postgresql_user_1: "user1".to_string(),
+                    postgresql_user_1_password: "secret1".to_string(),
+                    postgresql_user_2: "user2".to_string(),
+                    postgresql_user_2_password: "secret2".to_string(),
+                }),
+            },
+            SecretVersion {
+                version: 1,
+                created_time: "2024-05-01T10:00:00.000000Z".to_string(),
+                vault_structure: None,
+            },
+        ];
+
+        let history = format_history(&versions);
+
+        assert!(!history.contains("secret"));
+        assert_eq!(
+            history.lines().collect::<Vec<&str>>(),
+            vec![
+                "VERSION  CREATED                          ACTIVE SLOT  ACTIVE USER          USER 1               USER 2",
+                "2        2024-05-02T10:00:00.000000Z      user_2       user2                user1                user2",
+                "1        2024-05-01T10:00:00.000000Z      (deleted or unreadable)",
+            ]
+        );
+    }
+}
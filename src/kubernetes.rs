@@ -0,0 +1,275 @@
+// Copyright (c) 2024 PostFinance AG
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::ByteString;
+use kube::api::PostParams;
+use kube::config::{KubeConfigOptions, Kubeconfig};
+use kube::{Api, Client};
+use log::{debug, info, warn};
+use serde_json::{Map, Value};
+use tokio::runtime::{Builder, Runtime};
+
+use crate::config::{Config, KubernetesConfig, VaultKeysConfig};
+use crate::secret_store::SecretStore;
+use crate::vault::{VaultStructure, MAX_WRITE_ATTEMPTS};
+
+/// Stores the users and passwords in a Kubernetes Secret, for clusters without Vault.
+pub(crate) struct KubernetesSecretStore {
+    secrets: Api<Secret>,
+    kubernetes_config: KubernetesConfig,
+    namespace: String,
+    rt: Runtime,
+}
+
+impl KubernetesSecretStore {
+    pub(crate) fn connect(config: &Config) -> KubernetesSecretStore {
+        let kubernetes_config = config
+            .kubernetes
+            .clone()
+            .expect("Missing Kubernetes configuration");
+
+        let rt = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to build Kubernetes connection");
+
+        // The kube client builds its TLS configuration from the process-wide crypto provider
+        let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+
+        let client = rt
+            .block_on(Self::get_client(&kubernetes_config))
+            .unwrap_or_else(|e| panic!("Failed to connect to Kubernetes: {e}"));
+
+        let namespace = kubernetes_config
+            .namespace
+            .clone()
+            .unwrap_or_else(|| client.default_namespace().to_string());
+
+        debug!(
+            "Using Kubernetes Secret '{}' in namespace '{namespace}'",
+            kubernetes_config.secret
+        );
+
+        KubernetesSecretStore {
+            secrets: Api::namespaced(client, &namespace),
+            kubernetes_config,
+            namespace,
+            rt,
+        }
+    }
+
+    /// Loads the given kubeconfig, or else infers the configuration: the default kubeconfig if present, the in-cluster
+    /// service account otherwise.
+    async fn get_client(kubernetes_config: &KubernetesConfig) -> Result<Client, String> {
+        let options = KubeConfigOptions {
+            context: kubernetes_config.context.clone(),
+            ..KubeConfigOptions::default()
+        };
+
+        let client_config = match (&kubernetes_config.kubeconfig_path, &options.context) {
+            (Some(kubeconfig_path), _) => {
+                let kubeconfig =
+                    Kubeconfig::read_from(kubeconfig_path).map_err(|e| e.to_string())?;
+                kube::Config::from_custom_kubeconfig(kubeconfig, &options)
+                    .await
+                    .map_err(|e| e.to_string())?
+            }
+            (None, Some(_)) => kube::Config::from_kubeconfig(&options)
+                .await
+                .map_err(|e| e.to_string())?,
+            (None, None) => kube::Config::infer().await.map_err(|e| e.to_string())?,
+        };
+
+        Client::try_from(client_config).map_err(|e| e.to_string())
+    }
+
+    fn read_raw_secret(&mut self) -> Result<Option<Secret>, String> {
+        self.rt
+            .block_on(self.secrets.get_opt(&self.kubernetes_config.secret))
+            .map_err(|e| e.to_string())
+    }
+
+    /// Merges the users and passwords into the current Secret and writes it back. The `resourceVersion` of the Secret
+    /// read before makes the update fail if it changed in between. Returns `false` in that case, so the write should be
+    /// attempted again.
+    fn try_write_secret(&mut self, vault_structure: &VaultStructure) -> Result<bool, String> {
+        let keys = self.get_keys();
+        let current = self.read_raw_secret()?;
+
+        // Other keys stored in the same Secret must survive the write
+        let mut data =
+            secret_data_to_value(current.as_ref().and_then(|secret| secret.data.as_ref()))?;
+        vault_structure.to_secret(&mut data, &keys)?;
+        let data = value_to_secret_data(&data)?;
+
+        let post_params = PostParams::default();
+        let result = match current {
+            Some(mut secret) => {
+                secret.data = Some(data);
+                self.rt.block_on(self.secrets.replace(
+                    &self.kubernetes_config.secret,
+                    &post_params,
+                    &secret,
+                ))
+            }
+            None => {
+                let secret = Secret {
+                    metadata: ObjectMeta {
+                        name: Some(self.kubernetes_config.secret.clone()),
+                        namespace: Some(self.namespace.clone()),
+                        ..ObjectMeta::default()
+                    },
+                    data: Some(data),
+                    ..Secret::default()
+                };
+                self.rt.block_on(self.secrets.create(&post_params, &secret))
+            }
+        };
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(kube::Error::Api(status)) if status.is_conflict() || status.is_already_exists() => {
+                Ok(false)
+            }
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn get_keys(&self) -> VaultKeysConfig {
+        self.kubernetes_config.keys.clone().unwrap_or_default()
+    }
+}
+
+impl SecretStore for KubernetesSecretStore {
+    fn location(&self) -> String {
+        format!("{}/{}", self.namespace, self.kubernetes_config.secret)
+    }
+
+    fn init_secret_path(&mut self) {
+        info!("Initializing Kubernetes Secret '{}'", self.location());
+
+        let vault_structure = VaultStructure {
+            postgresql_active_user: "TBD".to_string(),
+            postgresql_active_user_password: "TBD".to_string(),
+            postgresql_user_1: "TBD".to_string(),
+            postgresql_user_1_password: "TBD".to_string(),
+            postgresql_user_2: "TBD".to_string(),
+            postgresql_user_2_password: "TBD".to_string(),
+        };
+
+        self.write_secret(&vault_structure)
+            .expect("Failed to create initial Kubernetes Secret");
+
+        println!(
+            "Successfully initialized Kubernetes Secret '{}'",
+            self.location()
+        )
+    }
+
+    fn read_secret(&mut self) -> Result<VaultStructure, String> {
+        info!("Reading Kubernetes Secret '{}'", self.location());
+
+        let secret = self
+            .read_raw_secret()?
+            .ok_or_else(|| format!("No Kubernetes Secret '{}' found", self.location()))?;
+
+        VaultStructure::from_secret(
+            &secret_data_to_value(secret.data.as_ref())?,
+            &self.get_keys(),
+        )
+    }
+
+    fn write_secret(&mut self, vault_structure: &VaultStructure) -> Result<(), String> {
+        info!("Writing Kubernetes Secret '{}'", self.location());
+
+        for attempt in 1..=MAX_WRITE_ATTEMPTS {
+            if self.try_write_secret(vault_structure)? {
+                return Ok(());
+            }
+
+            warn!("Kubernetes Secret changed while writing it (attempt {attempt}/{MAX_WRITE_ATTEMPTS}), reading it again");
+        }
+
+        Err(format!(
+            "Failed to write Kubernetes Secret '{}': it kept changing concurrently",
+            self.location()
+        ))
+    }
+}
+
+fn secret_data_to_value(data: Option<&BTreeMap<String, ByteString>>) -> Result<Value, String> {
+    let mut secret = Map::new();
+
+    for (key, value) in data.into_iter().flatten() {
+        let value = String::from_utf8(value.0.clone())
+            .map_err(|_| format!("Key '{key}' in Kubernetes Secret is not valid UTF-8"))?;
+        secret.insert(key.clone(), Value::String(value));
+    }
+
+    Ok(Value::Object(secret))
+}
+
+/// The data of a Kubernetes Secret is flat, hence nested keys configured as JSON pointers are rejected.
+fn value_to_secret_data(secret: &Value) -> Result<BTreeMap<String, ByteString>, String> {
+    secret
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(key, value)| match value {
+            Value::String(value) => Ok((key.clone(), ByteString(value.as_bytes().to_vec()))),
+            _ => Err(format!(
+                "Key '{key}' in Kubernetes Secret is not a string - JSON pointers are not supported"
+            )),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn secret_data_round_trip() {
+        let data = BTreeMap::from([
+            ("api_key".to_string(), ByteString(b"key".to_vec())),
+            (
+                "postgresql_active_user".to_string(),
+                ByteString(b"user1".to_vec()),
+            ),
+        ]);
+
+        let value = secret_data_to_value(Some(&data)).unwrap();
+
+        assert_eq!(
+            value,
+            json!({"api_key": "key", "postgresql_active_user": "user1"})
+        );
+        assert_eq!(value_to_secret_data(&value).unwrap(), data);
+    }
+
+    #[test]
+    fn secret_data_of_missing_secret() {
+        assert_eq!(secret_data_to_value(None).unwrap(), json!({}));
+    }
+
+    #[test]
+    fn value_to_secret_data_rejects_nested_keys() {
+        let value = json!({"slots": {"blue": {"user": "user1"}}});
+
+        assert_eq!(
+            value_to_secret_data(&value),
+            Err(
+                "Key 'slots' in Kubernetes Secret is not a string - JSON pointers are not supported"
+                    .to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,402 @@
+// Copyright (c) 2024 PostFinance AG
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+
+use std::env;
+use std::process;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::{debug, error, info, warn};
+use postgres::Client;
+use rand::random;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, LockConfig, VaultLockConfig};
+use crate::database::PostgresClient;
+use crate::secret_store::SecretStore;
+use crate::vault::{Vault, WriteResult};
+
+const DEFAULT_LOCK_TTL_SECONDS: u32 = 900;
+
+/// First key of the two-key PostgreSQL advisory locks taken by propeller, "prop" in ASCII.
+const ADVISORY_LOCK_CLASS: i32 = 0x7072_6f70;
+
+/// An exclusive lease held for the length of a rotation.
+pub(crate) trait RotationLock {
+    /// Called before the first secret write. From then on, a failed run keeps the lock until it expires: pods of the
+    /// failed rollout may still use the previously active user, whose password a retry would change.
+    fn hold_on_failure(&mut self);
+
+    fn release(&mut self);
+}
+
+/// Acquires the configured lock, or panics if another run holds it. Returns `None` if no lock has been configured.
+pub(crate) fn acquire_lock(
+    config: &Config,
+    secret_store: &mut dyn SecretStore,
+    break_lock: bool,
+) -> Option<Box<dyn RotationLock>> {
+    match config.lock.clone()? {
+        LockConfig::Vault(vault_lock_config) => Some(Box::new(VaultLock::acquire(
+            config,
+            vault_lock_config,
+            break_lock,
+        ))),
+        LockConfig::Postgres => Some(Box::new(PostgresLock::acquire(
+            config,
+            secret_store,
+            break_lock,
+        ))),
+    }
+}
+
+/// A lock record stored in Vault. A background thread renews the record while the rotation runs, however long the
+/// rollout takes, and deletes it once the lock is released. The record only expires if its holder crashed, or failed
+/// after writing the secret. The lock requires the Vault secret store, but uses a connection of its own to it.
+struct VaultLock {
+    path: String,
+    renewal: Option<LockRenewal>,
+    hold_on_failure: bool,
+}
+
+struct LockRenewal {
+    stop_sender: Sender<LockStop>,
+    thread: JoinHandle<Result<(), String>>,
+}
+
+/// What the renewal does with the lock record once it stops.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LockStop {
+    /// Delete the record, the next run may start right away.
+    Release,
+    /// Leave the record to expire.
+    Hold,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct LockRecord {
+    owner: String,
+    expires_at: u64,
+}
+
+impl VaultLock {
+    fn acquire(config: &Config, vault_lock_config: VaultLockConfig, break_lock: bool) -> VaultLock {
+        let mut vault = Vault::connect(config);
+        let path = vault_lock_config
+            .path
+            .unwrap_or_else(|| format!("{}.lock", vault.location()));
+        let ttl_seconds = vault_lock_config
+            .ttl_seconds
+            .unwrap_or(DEFAULT_LOCK_TTL_SECONDS);
+        let owner = lock_owner();
+
+        debug!("Acquiring Vault lock '{path}' as '{owner}'");
+
+        let existing = vault
+            .read_raw_secret(&path)
+            .unwrap_or_else(|e| panic!("Failed to read Vault lock '{path}': {e}"));
+
+        if let Some(existing) = &existing {
+            let record: LockRecord = serde_json::from_value(existing.data.clone())
+                .unwrap_or_else(|e| panic!("Failed to parse Vault lock '{path}': {e}"));
+            let now = unix_timestamp();
+
+            if record.expires_at > now {
+                if !break_lock {
+                    panic!(
+                        "Failed to acquire Vault lock '{path}': held by '{}' for another {} seconds - use --break-lock if it is stale",
+                        record.owner,
+                        record.expires_at - now
+                    );
+                }
+
+                warn!("Breaking Vault lock '{path}' held by '{}'", record.owner);
+            } else {
+                info!(
+                    "Taking over expired Vault lock '{path}' from '{}'",
+                    record.owner
+                );
+            }
+        }
+
+        match write_lock_record(
+            &mut vault,
+            &path,
+            &owner,
+            ttl_seconds,
+            existing.and_then(|existing| existing.version),
+        ) {
+            Ok(WriteResult::Written { .. }) => {}
+            Ok(WriteResult::Conflict) => {
+                panic!(
+                    "Failed to acquire Vault lock '{path}': acquired concurrently by another run"
+                )
+            }
+            Err(e) => panic!("Failed to acquire Vault lock '{path}': {e}"),
+        }
+
+        info!("Acquired Vault lock '{path}' for {ttl_seconds} seconds, renewed until released");
+
+        let (stop_sender, stop_receiver) = channel();
+        let renewal_path = path.clone();
+        let thread = thread::spawn(move || {
+            renew_until_released(vault, &renewal_path, &owner, ttl_seconds, stop_receiver)
+        });
+
+        VaultLock {
+            path,
+            renewal: Some(LockRenewal {
+                stop_sender,
+                thread,
+            }),
+            hold_on_failure: false,
+        }
+    }
+
+    /// Stops the renewal, which then deletes or leaves the lock record. Does nothing if the renewal stopped already.
+    fn stop_renewal(&mut self, stop: LockStop) -> Result<(), String> {
+        let Some(renewal) = self.renewal.take() else {
+            return Ok(());
+        };
+
+        // The thread also stops if the receiver is gone, so a failed send can be ignored
+        let _ = renewal.stop_sender.send(stop);
+
+        renewal
+            .thread
+            .join()
+            .unwrap_or_else(|_| Err("lock renewal panicked".to_string()))
+    }
+}
+
+impl RotationLock for VaultLock {
+    fn hold_on_failure(&mut self) {
+        self.hold_on_failure = true;
+    }
+
+    fn release(&mut self) {
+        if let Err(e) = self.stop_renewal(LockStop::Release) {
+            panic!("Failed to release Vault lock '{}': {e}", self.path);
+        }
+    }
+}
+
+/// A rotation failing before the first secret write releases the lock, as nothing of it is left that a retry could
+/// interfere with. Afterwards, the record is left to expire, unless a retry uses `--break-lock`.
+impl Drop for VaultLock {
+    fn drop(&mut self) {
+        let stop = if self.hold_on_failure {
+            LockStop::Hold
+        } else {
+            LockStop::Release
+        };
+
+        if let Err(e) = self.stop_renewal(stop) {
+            warn!("Failed to release Vault lock '{}': {e}", self.path);
+        }
+    }
+}
+
+fn write_lock_record(
+    vault: &mut Vault,
+    path: &str,
+    owner: &str,
+    ttl_seconds: u32,
+    version: Option<u64>,
+) -> Result<WriteResult, String> {
+    let record = LockRecord {
+        owner: owner.to_string(),
+        expires_at: unix_timestamp() + ttl_seconds as u64,
+    };
+
+    vault.write_raw_secret(
+        path,
+        &serde_json::to_value(&record).expect("Failed to serialize Vault lock"),
+        version,
+    )
+}
+
+/// Pushes the expiry of the lock record forward until told to stop, then deletes the record if this run still owns it.
+fn renew_until_released(
+    mut vault: Vault,
+    path: &str,
+    owner: &str,
+    ttl_seconds: u32,
+    stop_receiver: Receiver<LockStop>,
+) -> Result<(), String> {
+    // Renewing at a third of the TTL leaves room for a failed renewal before the record expires
+    let renewal_interval = Duration::from_secs((ttl_seconds / 3).max(1) as u64);
+    let mut held = true;
+
+    let stop = loop {
+        match stop_receiver.recv_timeout(renewal_interval) {
+            Ok(stop) => break stop,
+            Err(RecvTimeoutError::Disconnected) => break LockStop::Release,
+            Err(RecvTimeoutError::Timeout) if !held => continue,
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+
+        match read_lock_record(&mut vault, path) {
+            Ok(Some((record, version))) if record.owner == owner => {
+                match write_lock_record(&mut vault, path, owner, ttl_seconds, version) {
+                    Ok(WriteResult::Written { .. }) => debug!("Renewed Vault lock '{path}'"),
+                    Ok(WriteResult::Conflict) => {
+                        error!("Lost Vault lock '{path}': taken over by another run");
+                        held = false;
+                    }
+                    Err(e) => warn!("Failed to renew Vault lock '{path}': {e}"),
+                }
+            }
+            Ok(_) => {
+                error!("Lost Vault lock '{path}': taken over by another run");
+                held = false;
+            }
+            Err(e) => warn!("Failed to renew Vault lock '{path}': {e}"),
+        }
+    };
+
+    if stop == LockStop::Hold {
+        warn!("Keeping Vault lock '{path}' until it expires, as the failed rotation already wrote the secret - use --break-lock once its rollout has been checked");
+        return Ok(());
+    }
+
+    match read_lock_record(&mut vault, path)? {
+        Some((record, _)) if record.owner == owner => {
+            vault.delete_raw_secret(path)?;
+            info!("Released Vault lock '{path}'");
+        }
+        _ => warn!("Vault lock '{path}' is not held by this run anymore, leaving it untouched"),
+    }
+
+    Ok(())
+}
+
+fn read_lock_record(
+    vault: &mut Vault,
+    path: &str,
+) -> Result<Option<(LockRecord, Option<u64>)>, String> {
+    Ok(vault.read_raw_secret(path)?.and_then(|existing| {
+        serde_json::from_value(existing.data)
+            .ok()
+            .map(|record| (record, existing.version))
+    }))
+}
+
+/// A session-level advisory lock, released by PostgreSQL as soon as the connection of its holder ends. It doesn't expire
+/// while propeller runs, and a failed rotation releases it by dropping the connection, even after the first secret
+/// write.
+struct PostgresLock {
+    client: Client,
+    name: String,
+}
+
+impl PostgresLock {
+    fn acquire(
+        config: &Config,
+        secret_store: &mut dyn SecretStore,
+        break_lock: bool,
+    ) -> PostgresLock {
+        let secret = secret_store
+            .read_secret()
+            .unwrap_or_else(|e| panic!("Failed to read secret for PostgreSQL lock: {e}"));
+        let mut client = PostgresClient::init(config).connect_for_lock(
+            secret.postgresql_active_user,
+            secret.postgresql_active_user_password,
+        );
+        let name = format!("propeller:{}", secret_store.location());
+
+        debug!("Acquiring PostgreSQL lock '{name}'");
+
+        if break_lock {
+            let terminated: i64 = client
+                .query_one(
+                    "SELECT count(pg_terminate_backend(pid)) FROM pg_locks WHERE locktype = 'advisory' AND classid = $1::int4::oid AND objid = hashtext($2)::oid AND objsubid = 2",
+                    &[&ADVISORY_LOCK_CLASS, &name],
+                )
+                .unwrap_or_else(|e| panic!("Failed to break PostgreSQL lock '{name}': {e}"))
+                .get(0);
+            warn!("Breaking PostgreSQL lock '{name}' terminated {terminated} session(s)");
+        }
+
+        let acquired: bool = client
+            .query_one(
+                "SELECT pg_try_advisory_lock($1, hashtext($2))",
+                &[&ADVISORY_LOCK_CLASS, &name],
+            )
+            .unwrap_or_else(|e| panic!("Failed to acquire PostgreSQL lock '{name}': {e}"))
+            .get(0);
+
+        if !acquired {
+            panic!("Failed to acquire PostgreSQL lock '{name}': another rotation is in progress - use --break-lock if it is stale");
+        }
+
+        info!("Acquired PostgreSQL lock '{name}'");
+
+        PostgresLock { client, name }
+    }
+}
+
+impl RotationLock for PostgresLock {
+    /// The session, and with it the lock, ends with the process, so there is nothing to hold on to.
+    fn hold_on_failure(&mut self) {}
+
+    fn release(&mut self) {
+        let name = self.name.as_str();
+
+        self.client
+            .query_one(
+                "SELECT pg_advisory_unlock($1, hashtext($2))",
+                &[&ADVISORY_LOCK_CLASS, &name],
+            )
+            .unwrap_or_else(|e| panic!("Failed to release PostgreSQL lock '{name}': {e}"));
+
+        info!("Released PostgreSQL lock '{name}'");
+    }
+}
+
+fn lock_owner() -> String {
+    format!(
+        "{}/{}/{:08x}",
+        env::var("HOSTNAME").unwrap_or_else(|_| "unknown".to_string()),
+        process::id(),
+        random::<u32>()
+    )
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Failed to read system time")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_record_serialization() {
+        let record = LockRecord {
+            owner: "propeller-abc/1/0000002a".to_string(),
+            expires_at: 1_700_000_000,
+        };
+
+        let value = serde_json::to_value(&record).unwrap();
+
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "owner": "propeller-abc/1/0000002a",
+                "expires_at": 1_700_000_000
+            })
+        );
+    }
+
+    #[test]
+    fn lock_owner_is_unique() {
+        assert_ne!(lock_owner(), lock_owner());
+    }
+}
@@ -9,14 +9,24 @@ use env_logger::{Env, DEFAULT_WRITE_STYLE_ENV};
 use crate::argo_cd::ArgoCD;
 use crate::cli::{CliArgs, Command};
 use crate::config::{read_config, Config};
+use crate::history::{print_secret_history, restore_secret_version};
+use crate::secret_store::{init_secret_store, SecretStore};
 use crate::vault::Vault;
 use crate::workflow::rotate_secrets_using_switch_method;
 
 mod argo_cd;
+mod aws_secrets_manager;
+mod cassandra;
 mod cli;
+mod clickhouse;
 mod config;
 mod database;
+mod history;
+mod kubernetes;
+mod lock;
 mod password;
+mod plugin;
+mod secret_store;
 mod vault;
 mod workflow;
 
@@ -28,14 +38,29 @@ fn main() {
     match args.command {
         Command::InitVault(int_args) => {
             let config: Config = read_config(int_args.base.config_path.clone());
-            let mut vault: Vault = Vault::connect(&config);
-            vault.init_secret_path()
+            let mut secret_store: Box<dyn SecretStore> = init_secret_store(&config);
+            secret_store.init_secret_path()
         }
         Command::Rotate(rotate_args) => {
             let config: Config = read_config(rotate_args.base.config_path.clone());
             let mut argo_cd: ArgoCD = ArgoCD::init(&config);
+            let mut secret_store: Box<dyn SecretStore> = init_secret_store(&config);
+            rotate_secrets_using_switch_method(
+                &rotate_args,
+                &config,
+                &mut argo_cd,
+                secret_store.as_mut(),
+            )
+        }
+        Command::History(history_args) => {
+            let config: Config = read_config(history_args.base.config_path.clone());
+            let mut vault: Vault = Vault::connect(&config);
+            print_secret_history(&mut vault)
+        }
+        Command::Restore(restore_args) => {
+            let config: Config = read_config(restore_args.base.config_path.clone());
             let mut vault: Vault = Vault::connect(&config);
-            rotate_secrets_using_switch_method(&rotate_args, &config, &mut argo_cd, &mut vault)
+            restore_secret_version(&restore_args, &config, &mut vault)
         }
     }
 }
@@ -0,0 +1,324 @@
+// Copyright (c) 2024 PostFinance AG
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+
+use std::io::{Read, Write};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread::{self, sleep, JoinHandle};
+use std::time::{Duration, Instant};
+
+use log::{debug, trace};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, PluginConfig};
+use crate::database::DatabaseClient;
+
+const PLUGIN_PROTOCOL_VERSION: u8 = 1;
+const TIMEOUT_SECONDS: u16 = 30;
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Delegates password changes to an external executable.
+///
+/// Each operation spawns the executable once, writes a single JSON request to its stdin and expects a single JSON
+/// response on its stdout. See the "Plugin Protocol" chapter in the README for the full specification.
+pub(crate) struct PluginClient {
+    plugin_config: PluginConfig,
+}
+
+#[derive(Debug, Serialize)]
+struct PluginRequest<'a> {
+    protocol_version: u8,
+    #[serde(flatten)]
+    operation: PluginOperation<'a>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+enum PluginOperation<'a> {
+    Verify {
+        user: &'a str,
+        password: &'a str,
+    },
+    SetPassword {
+        user: &'a str,
+        old_password: &'a str,
+        new_password: &'a str,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct PluginResponse {
+    success: bool,
+    message: Option<String>,
+}
+
+impl PluginClient {
+    pub(crate) fn init(config: &Config) -> PluginClient {
+        let plugin_config = config.plugin.clone().expect("Missing plugin configuration");
+
+        debug!(
+            "Using plugin executable: {}",
+            plugin_config.executable.display()
+        );
+
+        PluginClient { plugin_config }
+    }
+
+    fn invoke(&self, operation: PluginOperation) -> Result<(), String> {
+        let request = serde_json::to_string(&PluginRequest {
+            protocol_version: PLUGIN_PROTOCOL_VERSION,
+            operation,
+        })
+        .expect("Failed to serialize plugin request");
+
+        let mut child = Command::new(&self.plugin_config.executable)
+            .args(self.plugin_config.args.clone().unwrap_or_default())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                format!(
+                    "Failed to launch '{}': {e}",
+                    self.plugin_config.executable.display()
+                )
+            })?;
+
+        // Read both pipes while writing, so that neither side blocks on a full pipe
+        let stdout = read_in_background(child.stdout.take().expect("Failed to open plugin stdout"));
+        let stderr = read_in_background(child.stderr.take().expect("Failed to open plugin stderr"));
+
+        // Closes stdin once written; a plugin exiting early makes this fail, but its stderr tells why
+        let write_result = child
+            .stdin
+            .take()
+            .expect("Failed to open plugin stdin")
+            .write_all(request.as_bytes());
+
+        let status = self.wait_with_timeout(&mut child)?;
+        let stdout = stdout.join().expect("Failed to read plugin stdout");
+        let stderr = stderr.join().expect("Failed to read plugin stderr");
+        let stderr = String::from_utf8_lossy(&stderr);
+
+        let Some(status) = status else {
+            return Err(format!(
+                "Plugin did not finish within {} seconds and was killed: {}",
+                self.get_timeout_seconds(),
+                stderr.trim()
+            ));
+        };
+
+        if !status.success() {
+            return Err(format!("Plugin exited with {status}: {}", stderr.trim()));
+        }
+
+        if let Err(e) = write_result {
+            return Err(format!(
+                "Failed to write plugin request: {e}: {}",
+                stderr.trim()
+            ));
+        }
+
+        let response: PluginResponse = serde_json::from_slice(&stdout)
+            .map_err(|e| format!("Failed to parse plugin response: {e}"))?;
+
+        if response.success {
+            Ok(())
+        } else {
+            Err(response
+                .message
+                .unwrap_or_else(|| "Plugin reported a failure without message".to_string()))
+        }
+    }
+
+    /// Waits for the plugin to exit, or kills it once the timeout expired, in which case there is no exit status.
+    fn wait_with_timeout(&self, child: &mut Child) -> Result<Option<ExitStatus>, String> {
+        let deadline = Instant::now() + Duration::from_secs(self.get_timeout_seconds() as u64);
+
+        loop {
+            if let Some(status) = child
+                .try_wait()
+                .map_err(|e| format!("Failed to wait for plugin: {e}"))?
+            {
+                return Ok(Some(status));
+            }
+
+            if Instant::now() >= deadline {
+                child
+                    .kill()
+                    .and_then(|_| child.wait())
+                    .map_err(|e| format!("Failed to kill plugin: {e}"))?;
+                return Ok(None);
+            }
+
+            sleep(EXIT_POLL_INTERVAL);
+        }
+    }
+
+    fn get_timeout_seconds(&self) -> u16 {
+        self.plugin_config
+            .timeout_seconds
+            .unwrap_or(TIMEOUT_SECONDS)
+    }
+}
+
+fn read_in_background(mut pipe: impl Read + Send + 'static) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        // A pipe closed early just yields what was written so far
+        let _ = pipe.read_to_end(&mut buffer);
+        buffer
+    })
+}
+
+impl DatabaseClient for PluginClient {
+    fn update_password(&self, username: String, password: String, new_password: String) {
+        self.invoke(PluginOperation::SetPassword {
+            user: &username,
+            old_password: &password,
+            new_password: &new_password,
+        })
+        .unwrap_or_else(|e| panic!("Failed to update password of '{username}': {e}"));
+
+        self.invoke(PluginOperation::Verify {
+            user: &username,
+            password: &new_password,
+        })
+        .unwrap_or_else(|e| panic!("Failed to verify login of '{username}': {e}"));
+
+        trace!("Updated password of '{username}' and verified login");
+    }
+
+    fn verify_password(&self, username: &str, password: &str) -> bool {
+        self.invoke(PluginOperation::Verify {
+            user: username,
+            password,
+        })
+        .is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use crate::config::{ArgoConfig, VaultConfig};
+
+    #[test]
+    fn set_password_request() {
+        let request = serde_json::to_value(PluginRequest {
+            protocol_version: PLUGIN_PROTOCOL_VERSION,
+            operation: PluginOperation::SetPassword {
+                user: "user1",
+                old_password: "old",
+                new_password: "new",
+            },
+        })
+        .unwrap();
+
+        assert_eq!(
+            request,
+            serde_json::json!({
+                "protocol_version": 1,
+                "operation": "set_password",
+                "user": "user1",
+                "old_password": "old",
+                "new_password": "new"
+            })
+        );
+    }
+
+    #[test]
+    fn verify_request() {
+        let request = serde_json::to_value(PluginRequest {
+            protocol_version: PLUGIN_PROTOCOL_VERSION,
+            operation: PluginOperation::Verify {
+                user: "user1",
+                password: "secret",
+            },
+        })
+        .unwrap();
+
+        assert_eq!(
+            request,
+            serde_json::json!({
+                "protocol_version": 1,
+                "operation": "verify",
+                "user": "user1",
+                "password": "secret"
+            })
+        );
+    }
+
+    #[test]
+    fn update_password_successful_plugin() {
+        let fixture = PluginClient::init(&create_config_with_plugin(
+            "cat > /dev/null; echo '{\"success\": true}'",
+        ));
+
+        fixture.update_password("user1".to_string(), "old".to_string(), "new".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Failed to update password of 'user1': user is locked")]
+    fn update_password_failing_plugin() {
+        let fixture = PluginClient::init(&create_config_with_plugin(
+            "cat > /dev/null; echo '{\"success\": false, \"message\": \"user is locked\"}'",
+        ));
+
+        fixture.update_password("user1".to_string(), "old".to_string(), "new".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Failed to update password of 'user1': Plugin exited with")]
+    fn update_password_crashing_plugin() {
+        let fixture = PluginClient::init(&create_config_with_plugin(
+            "cat > /dev/null; echo 'boom' >&2; exit 3",
+        ));
+
+        fixture.update_password("user1".to_string(), "old".to_string(), "new".to_string());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to update password of 'user1': Plugin did not finish within 1 seconds and was killed: hanging"
+    )]
+    fn update_password_hanging_plugin() {
+        let mut config = create_config_with_plugin("echo 'hanging' >&2; exec sleep 30");
+        config.plugin.as_mut().unwrap().timeout_seconds = Some(1);
+        let fixture = PluginClient::init(&config);
+
+        fixture.update_password("user1".to_string(), "old".to_string(), "new".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Failed to update password of 'user1': Plugin exited with")]
+    fn update_password_plugin_exiting_before_reading() {
+        let fixture = PluginClient::init(&create_config_with_plugin(
+            "echo 'unsupported protocol' >&2; exit 2",
+        ));
+
+        fixture.update_password("user1".to_string(), "old".to_string(), "new".to_string());
+    }
+
+    fn create_config_with_plugin(script: &str) -> Config {
+        Config {
+            argo_cd: ArgoConfig::default(),
+            aws_secrets_manager: None,
+            cassandra: None,
+            clickhouse: None,
+            kubernetes: None,
+            lock: None,
+            plugin: Some(PluginConfig {
+                executable: PathBuf::from("sh"),
+                args: Some(vec!["-c".to_string(), script.to_string()]),
+                timeout_seconds: None,
+            }),
+            postgres: None,
+            vault: Some(VaultConfig::default()),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright (c) 2024 PostFinance AG
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+
+use crate::aws_secrets_manager::AwsSecretsManager;
+use crate::config::Config;
+use crate::kubernetes::KubernetesSecretStore;
+use crate::vault::{Vault, VaultStructure};
+
+/// Where the users and passwords are kept, and where the application reads the active one from.
+pub(crate) trait SecretStore {
+    /// A human-readable name of the secret, e.g. its Vault path.
+    fn location(&self) -> String;
+
+    fn init_secret_path(&mut self);
+
+    fn read_secret(&mut self) -> Result<VaultStructure, String>;
+
+    fn write_secret(&mut self, vault_structure: &VaultStructure) -> Result<(), String>;
+
+    /// Stages the secret before the database changes, for stores promoting it with the following `write_secret`.
+    fn stage_secret(&mut self, _vault_structure: &VaultStructure) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// The secret staged by a run that was interrupted before promoting it, if the store stages secrets at all.
+    fn read_pending_secret(&mut self) -> Result<Option<VaultStructure>, String> {
+        Ok(None)
+    }
+
+    /// Drops the secret staged by an interrupted run, once it is known that the database didn't change.
+    fn discard_pending_secret(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Names the ArgoCD applications rolling out the secret, for stores recording them along with it.
+    fn set_argo_cd_applications(&mut self, _applications: &[String]) {}
+
+    /// Copies the active user and password to additional destinations, if the store supports any.
+    fn fan_out_secret(&mut self, _vault_structure: &VaultStructure) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+pub(crate) fn init_secret_store(config: &Config) -> Box<dyn SecretStore> {
+    if config.aws_secrets_manager.is_some() {
+        Box::new(AwsSecretsManager::connect(config))
+    } else if config.kubernetes.is_some() {
+        Box::new(KubernetesSecretStore::connect(config))
+    } else {
+        Box::new(Vault::connect(config))
+    }
+}
@@ -3,34 +3,36 @@
 // This software is released under the MIT License.
 // https://opensource.org/licenses/MIT
 
-use log::{debug, error, info, trace};
+use log::{debug, error, info, trace, warn};
 
 use crate::argo_cd::ArgoCD;
 use crate::cli::RotateArgs;
 use crate::config::Config;
-use crate::database::PostgresClient;
+use crate::database::{init_database_client, DatabaseClient};
+use crate::lock::{acquire_lock, RotationLock};
 use crate::password::generate_random_password;
-use crate::vault::{Vault, VaultStructure};
+use crate::secret_store::SecretStore;
+use crate::vault::VaultStructure;
 
 pub(crate) fn rotate_secrets_using_switch_method(
     rotate_args: &RotateArgs,
     config: &Config,
     argo_cd: &mut ArgoCD,
-    vault: &mut Vault,
+    secret_store: &mut dyn SecretStore,
 ) {
-    let db: PostgresClient = PostgresClient::init(config);
+    let db: Box<dyn DatabaseClient> = init_database_client(config);
+    let mut lock: Option<Box<dyn RotationLock>> =
+        acquire_lock(config, secret_store, rotate_args.break_lock);
 
     info!("Starting 'switch' workflow");
 
-    let mut secret: VaultStructure = vault.read_secret().unwrap_or_else(|e| {
-        error!(
-            "Failed to read Vault path '{}': {}",
-            config.vault.clone().path,
-            e
-        );
+    secret_store.set_argo_cd_applications(argo_cd.applications());
+
+    let mut secret: VaultStructure = secret_store.read_secret().unwrap_or_else(|e| {
+        error!("Failed to read secret '{}': {}", secret_store.location(), e);
         panic!(
             "Failed to read path '{}' - did you init Vault?",
-            config.vault.clone().path
+            secret_store.location()
         );
     });
 
@@ -40,31 +42,58 @@ pub(crate) fn rotate_secrets_using_switch_method(
         panic!("Failed to detect active user - did neither match user 1 nor 2")
     }
 
+    recover_pending_secret(db.as_ref(), secret_store, &mut secret);
+
     let new_password: String = generate_random_password(rotate_args.password_length);
 
-    update_passive_user_postgres_password(&db, &mut secret, new_password);
+    let password_change = set_passive_user_password(&mut secret, new_password);
     switch_active_user(&mut secret);
 
-    vault
+    secret_store
+        .stage_secret(&secret)
+        .expect("Failed to stage switched secret - the database is unchanged");
+
+    update_database_password(db.as_ref(), password_change);
+
+    if let Some(lock) = lock.as_mut() {
+        lock.hold_on_failure();
+    }
+
+    secret_store
         .write_secret(&secret)
         .expect("Failed to kick-off rotation workflow by switching active user - Vault is in an invalid state");
 
     debug!("Active and passive users switched and synchronized into Vault");
+
+    // Applications reading the fan-out paths need the new active user, before the password of the old one changes
+    secret_store.fan_out_secret(&secret).unwrap_or_else(|e| {
+        panic!("{e} - aborting before the ArgoCD sync, the previously active user is still valid")
+    });
+
     debug!("Starting ArgoCD rollout now");
 
-    argo_cd.sync();
-    argo_cd.wait_for_rollout();
+    argo_cd.rollout();
 
     debug!("ArgoCD rollout succeeded, continue changing password of previously active user");
 
     let new_password: String = generate_random_password(rotate_args.password_length);
 
-    update_passive_user_postgres_password(&db, &mut secret, new_password);
+    let password_change = set_passive_user_password(&mut secret, new_password);
 
-    vault
+    secret_store
+        .stage_secret(&secret)
+        .expect("Failed to stage PASSIVE user password after sync - the database is unchanged");
+
+    update_database_password(db.as_ref(), password_change);
+
+    secret_store
         .write_secret(&secret)
         .expect("Failed to update PASSIVE user password after sync - Vault is in an invalid state");
 
+    if let Some(lock) = lock.as_mut() {
+        lock.release();
+    }
+
     println!("Successfully rotated all secrets")
 }
 
@@ -88,34 +117,99 @@ fn switch_active_user(secret: &mut VaultStructure) {
     trace!("Switched active and passive user in Vault secret (locally)")
 }
 
-fn update_passive_user_postgres_password(
-    db: &PostgresClient,
-    secret: &mut VaultStructure,
+/// A new password of the passive user, set in the secret before the database changes.
+struct PasswordChange {
+    username: String,
+    password: String,
     new_password: String,
-) {
-    info!("Rotating database password of passive user");
+}
 
-    let (passive_user, passive_user_password) =
-        if secret.postgresql_active_user == secret.postgresql_user_1 {
-            let original_password = secret.postgresql_user_2_password.clone();
-            secret.postgresql_user_2_password.clone_from(&new_password);
-            (secret.postgresql_user_2.clone(), original_password)
-        } else {
-            let original_password = secret.postgresql_user_1_password.clone();
-            secret.postgresql_user_1_password.clone_from(&new_password);
-            (secret.postgresql_user_1.clone(), original_password)
-        };
+fn set_passive_user_password(secret: &mut VaultStructure, new_password: String) -> PasswordChange {
+    let (username, password) = if secret.postgresql_active_user == secret.postgresql_user_1 {
+        let original_password = secret.postgresql_user_2_password.clone();
+        secret.postgresql_user_2_password.clone_from(&new_password);
+        (secret.postgresql_user_2.clone(), original_password)
+    } else {
+        let original_password = secret.postgresql_user_1_password.clone();
+        secret.postgresql_user_1_password.clone_from(&new_password);
+        (secret.postgresql_user_1.clone(), original_password)
+    };
+
+    PasswordChange {
+        username,
+        password,
+        new_password,
+    }
+}
 
-    let mut client = db.connect_for_user(passive_user.clone(), passive_user_password);
-    let query = format!("ALTER ROLE {passive_user} WITH PASSWORD '{new_password}'");
+fn update_database_password(db: &dyn DatabaseClient, password_change: PasswordChange) {
+    info!("Rotating database password of passive user");
 
-    client
-        .execute(query.as_str(), &[])
-        .unwrap_or_else(|e| panic!("Failed to update password of '{passive_user}': {e}"));
+    db.update_password(
+        password_change.username,
+        password_change.password,
+        password_change.new_password,
+    );
 
     trace!("Successfully rotated database password of passive user");
 }
 
+/// A run interrupted between staging the secret and writing it may or may not have changed the password of the passive
+/// user. Whichever of the current and the pending password the database accepts is kept.
+fn recover_pending_secret(
+    db: &dyn DatabaseClient,
+    secret_store: &mut dyn SecretStore,
+    secret: &mut VaultStructure,
+) {
+    let pending = secret_store.read_pending_secret().unwrap_or_else(|e| {
+        panic!(
+            "Failed to read pending secret of '{}': {e}",
+            secret_store.location()
+        )
+    });
+    let Some(pending) = pending else {
+        return;
+    };
+
+    let (username, password, pending_password) =
+        if pending.postgresql_user_1_password != secret.postgresql_user_1_password {
+            (
+                secret.postgresql_user_1.clone(),
+                &mut secret.postgresql_user_1_password,
+                pending.postgresql_user_1_password,
+            )
+        } else if pending.postgresql_user_2_password != secret.postgresql_user_2_password {
+            (
+                secret.postgresql_user_2.clone(),
+                &mut secret.postgresql_user_2_password,
+                pending.postgresql_user_2_password,
+            )
+        } else {
+            secret_store
+                .discard_pending_secret()
+                .expect("Failed to discard pending secret");
+            return;
+        };
+
+    if db.verify_password(&username, &pending_password) {
+        warn!("The interrupted run already changed the password of '{username}', keeping the pending one");
+        *password = pending_password;
+        secret_store
+            .write_secret(secret)
+            .expect("Failed to write recovered password of passive user");
+    } else if db.verify_password(&username, password) {
+        warn!("The interrupted run didn't change the password of '{username}', discarding the pending one");
+        secret_store
+            .discard_pending_secret()
+            .expect("Failed to discard pending secret");
+    } else {
+        panic!(
+            "Failed to recover pending secret of '{}': '{username}' can log in with neither the current nor the pending password",
+            secret_store.location()
+        );
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -140,6 +234,106 @@ mod tests {
         assert_eq!(secret.postgresql_active_user_password, "password1");
     }
 
+    #[test]
+    fn recover_pending_secret_changed_database() {
+        let mut secret = create_vault_structure_active_user_1();
+        let mut pending = create_vault_structure_active_user_2();
+        pending.postgresql_user_2_password = "pending".to_string();
+        let mut secret_store = PendingSecretStore::new(pending);
+
+        recover_pending_secret(&LoginOnly("pending"), &mut secret_store, &mut secret);
+
+        assert_eq!(secret.postgresql_active_user, "user1");
+        assert_eq!(secret.postgresql_user_2_password, "pending");
+        assert_eq!(secret_store.written, Some(secret));
+        assert!(!secret_store.discarded);
+    }
+
+    #[test]
+    fn recover_pending_secret_unchanged_database() {
+        let mut secret = create_vault_structure_active_user_1();
+        let mut pending = create_vault_structure_active_user_2();
+        pending.postgresql_user_2_password = "pending".to_string();
+        let mut secret_store = PendingSecretStore::new(pending);
+
+        recover_pending_secret(&LoginOnly("password2"), &mut secret_store, &mut secret);
+
+        assert_eq!(secret, create_vault_structure_active_user_1());
+        assert_eq!(secret_store.written, None);
+        assert!(secret_store.discarded);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to recover pending secret of 'pending': 'user2' can log in with neither the current nor the pending password"
+    )]
+    fn recover_pending_secret_unknown_password() {
+        let mut secret = create_vault_structure_active_user_1();
+        let mut pending = create_vault_structure_active_user_1();
+        pending.postgresql_user_2_password = "pending".to_string();
+        let mut secret_store = PendingSecretStore::new(pending);
+
+        recover_pending_secret(&LoginOnly("other"), &mut secret_store, &mut secret);
+        // This should panic
+    }
+
+    /// Accepts a single password for every user.
+    struct LoginOnly(&'static str);
+
+    impl DatabaseClient for LoginOnly {
+        fn update_password(&self, _username: String, _password: String, _new_password: String) {
+            unimplemented!()
+        }
+
+        fn verify_password(&self, _username: &str, password: &str) -> bool {
+            password == self.0
+        }
+    }
+
+    struct PendingSecretStore {
+        pending: VaultStructure,
+        written: Option<VaultStructure>,
+        discarded: bool,
+    }
+
+    impl PendingSecretStore {
+        fn new(pending: VaultStructure) -> PendingSecretStore {
+            PendingSecretStore {
+                pending,
+                written: None,
+                discarded: false,
+            }
+        }
+    }
+
+    impl SecretStore for PendingSecretStore {
+        fn location(&self) -> String {
+            "pending".to_string()
+        }
+
+        fn init_secret_path(&mut self) {
+            unimplemented!()
+        }
+
+        fn read_secret(&mut self) -> Result<VaultStructure, String> {
+            unimplemented!()
+        }
+
+        fn write_secret(&mut self, vault_structure: &VaultStructure) -> Result<(), String> {
+            self.written = Some(vault_structure.clone());
+            Ok(())
+        }
+
+        fn read_pending_secret(&mut self) -> Result<Option<VaultStructure>, String> {
+            Ok(Some(self.pending.clone()))
+        }
+
+        fn discard_pending_secret(&mut self) -> Result<(), String> {
+            self.discarded = true;
+            Ok(())
+        }
+    }
+
     fn create_vault_structure_active_user_1() -> VaultStructure {
         VaultStructure {
             postgresql_active_user: "user1".to_string(),
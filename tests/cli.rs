@@ -21,6 +21,10 @@ fn propeller_cli() {
         ))
         .stderr(contains("rotate"))
         .stderr(contains("Rotate PostgreSQL database secrets"))
+        .stderr(contains("history"))
+        .stderr(contains("List earlier versions of the Vault secret"))
+        .stderr(contains("restore"))
+        .stderr(contains("Restore an earlier version of the Vault secret"))
         .stderr(contains("help"))
         .stderr(contains(
             "Print this message or the help of the given subcommand(s)",
@@ -85,3 +89,35 @@ fn propeller_cli_rotate_help() {
         .stdout(contains("-V, --version"))
         .stdout(contains("Print version"));
 }
+
+#[test]
+fn propeller_cli_history_help() {
+    Command::cargo_bin("propeller")
+        .unwrap()
+        .arg("history")
+        .arg("--help")
+        .stdout(Stdio::piped())
+        .assert()
+        .success()
+        .stdout(contains("List earlier versions of the Vault secret."))
+        .stdout(contains("history [OPTIONS]"))
+        .stdout(contains("-c, --config-path <CONFIG_PATH>"))
+        .stdout(contains("[default: config.yml]"));
+}
+
+#[test]
+fn propeller_cli_restore_help() {
+    Command::cargo_bin("propeller")
+        .unwrap()
+        .arg("restore")
+        .arg("--help")
+        .stdout(Stdio::piped())
+        .assert()
+        .success()
+        .stdout(contains("Restore an earlier version of the Vault secret."))
+        .stdout(contains("restore [OPTIONS] --version <VERSION>"))
+        .stdout(contains("-c, --config-path <CONFIG_PATH>"))
+        .stdout(contains("--version <VERSION>"))
+        .stdout(contains("--sync"))
+        .stdout(contains("--break-lock"));
+}
@@ -0,0 +1,126 @@
+// Copyright (c) 2024 PostFinance AG
+//
+// This software is released under the MIT License.
+// https://opensource.org/licenses/MIT
+
+use std::process::{Command, Stdio};
+
+use assert_cmd::prelude::*;
+use ntest::timeout;
+use predicates::prelude::*;
+use predicates::str::contains;
+use utilities::{
+    create_vault_client, read_vault_secret, vault_container, write_string_to_tempfile, VaultSecret,
+};
+use vaultrs::client::VaultClient;
+use vaultrs::kv2;
+
+#[tokio::test]
+#[timeout(30_000)]
+async fn history_lists_versions_without_passwords() {
+    let vault_container = vault_container().await;
+
+    let vault_host = vault_container.get_host().await.unwrap();
+    let vault_port = vault_container.get_host_port_ipv4(8200).await.unwrap();
+
+    let vault_client = create_vault_client(vault_host.to_string().as_str(), vault_port);
+    write_vault_secret_versions(&vault_client, "history/secret").await;
+
+    println!("Setup success; invoking propeller...");
+
+    Command::cargo_bin("propeller")
+        .unwrap()
+        .arg("history")
+        .arg("-c")
+        .arg(create_config(
+            vault_host.to_string().as_str(),
+            vault_port,
+            "history/secret",
+        ))
+        .env("VAULT_TOKEN", "root-token")
+        .env("PROPELLER_LOG_LEVEL", "info")
+        .stdout(Stdio::piped())
+        .assert()
+        .success()
+        .stdout(contains("VERSION"))
+        .stdout(contains("user_1"))
+        .stdout(contains("user_2"))
+        .stdout(contains("password").not());
+}
+
+#[tokio::test]
+#[timeout(30_000)]
+async fn restore_earlier_version() {
+    let vault_container = vault_container().await;
+
+    let vault_host = vault_container.get_host().await.unwrap();
+    let vault_port = vault_container.get_host_port_ipv4(8200).await.unwrap();
+
+    let vault_client = create_vault_client(vault_host.to_string().as_str(), vault_port);
+    write_vault_secret_versions(&vault_client, "restore/secret").await;
+
+    println!("Setup success; invoking propeller...");
+
+    Command::cargo_bin("propeller")
+        .unwrap()
+        .arg("restore")
+        .arg("-c")
+        .arg(create_config(
+            vault_host.to_string().as_str(),
+            vault_port,
+            "restore/secret",
+        ))
+        .arg("--version")
+        .arg("1")
+        .env("VAULT_TOKEN", "root-token")
+        .env("PROPELLER_LOG_LEVEL", "info")
+        .stdout(Stdio::piped())
+        .assert()
+        .success()
+        .stdout(contains(
+            "Successfully restored version 1 of Vault path 'restore/secret'",
+        ));
+
+    let vault_secret = read_vault_secret(&vault_client, "restore/secret").await;
+
+    assert_eq!(vault_secret.postgresql_active_user, "user1");
+    assert_eq!(vault_secret.postgresql_active_user_password, "password1");
+}
+
+async fn write_vault_secret_versions(vault_client: &VaultClient, secret_path: &str) {
+    for (active_user, active_user_password) in [("user1", "password1"), ("user2", "password2")] {
+        let vault_secret = VaultSecret {
+            postgresql_active_user: active_user.to_string(),
+            postgresql_active_user_password: active_user_password.to_string(),
+            postgresql_user_1: "user1".to_string(),
+            postgresql_user_1_password: "password1".to_string(),
+            postgresql_user_2: "user2".to_string(),
+            postgresql_user_2_password: "password2".to_string(),
+        };
+
+        kv2::set(vault_client, "secret", secret_path, &vault_secret)
+            .await
+            .expect("Failed to write Vault secret");
+    }
+}
+
+fn create_config(vault_host: &str, vault_port: u16, secret_path: &str) -> String {
+    write_string_to_tempfile(
+        format!(
+            // language=yaml
+            "
+argo_cd:
+  application: 'propeller'
+  base_url: 'http://localhost:3100'
+postgres:
+  host: 'localhost'
+  port: 5432
+  database: 'demo'
+vault:
+  base_url: 'http://{vault_host}:{vault_port}'
+  path: '{secret_path}'
+"
+        )
+        .as_str(),
+    )
+}
@@ -3,14 +3,18 @@
 // This software is released under the MIT License.
 // https://opensource.org/licenses/MIT
 
+use std::collections::HashMap;
 use std::process::{Command, Stdio};
 
 use assert_cmd::prelude::*;
 use ntest::timeout;
 use predicates::str::contains;
 use utilities::{
-    create_vault_client, read_vault_secret, vault_container, write_string_to_tempfile,
+    create_vault_client, localstack_container, read_aws_secret, read_vault_secret, vault_container,
+    write_string_to_tempfile, VaultSecret,
 };
+use vaultrs::sys::mount;
+use vaultrs::{kv1, kv2};
 
 #[tokio::test]
 #[timeout(30_000)]
@@ -64,6 +68,178 @@ vault:
     assert_eq!(vault_secret.postgresql_user_2_password, "TBD");
 }
 
+#[tokio::test]
+#[timeout(30_000)]
+async fn init_vault_preserves_unrelated_keys() {
+    let vault_container = vault_container().await;
+
+    let vault_host = vault_container.get_host().await.unwrap();
+    let vault_port = vault_container.get_host_port_ipv4(8200).await.unwrap();
+
+    let vault_client = create_vault_client(vault_host.to_string().as_str(), vault_port);
+    kv2::set(
+        &vault_client,
+        "secret",
+        "init/vault/existing/path",
+        &HashMap::from([("api_key", "my-api-key")]),
+    )
+    .await
+    .expect("Failed to write existing Vault secret");
+
+    println!("Setup success; invoking propeller...");
+
+    Command::cargo_bin("propeller")
+        .unwrap()
+        .arg("init-vault")
+        .arg("-c")
+        .arg(write_string_to_tempfile(
+            format!(
+                // language=yaml
+                "
+argo_cd:
+  application: 'propeller'
+  base_url: 'http://localhost:3100'
+postgres:
+  host: 'localhost'
+  port: 5432
+  database: 'demo'
+vault:
+  base_url: 'http://{vault_host}:{vault_port}'
+  path: 'init/vault/existing/path'
+"
+            )
+            .as_str(),
+        ))
+        .env("VAULT_TOKEN", "root-token")
+        .env("PROPELLER_LOG_LEVEL", "info")
+        .stdout(Stdio::piped())
+        .assert()
+        .success();
+
+    let vault_secret: HashMap<String, String> =
+        kv2::read(&vault_client, "secret", "init/vault/existing/path")
+            .await
+            .expect("Failed to read Vault secret");
+
+    assert_eq!(vault_secret["api_key"], "my-api-key");
+    assert_eq!(vault_secret["postgresql_active_user"], "TBD");
+}
+
+#[tokio::test]
+#[timeout(30_000)]
+async fn init_vault_kv1_mount() {
+    let vault_container = vault_container().await;
+
+    let vault_host = vault_container.get_host().await.unwrap();
+    let vault_port = vault_container.get_host_port_ipv4(8200).await.unwrap();
+
+    let vault_client = create_vault_client(vault_host.to_string().as_str(), vault_port);
+    mount::enable(&vault_client, "kv-legacy", "kv", None)
+        .await
+        .expect("Failed to enable KV v1 secrets engine");
+
+    println!("Setup success; invoking propeller...");
+
+    Command::cargo_bin("propeller")
+        .unwrap()
+        .arg("init-vault")
+        .arg("-c")
+        .arg(write_string_to_tempfile(
+            format!(
+                // language=yaml
+                "
+argo_cd:
+  application: 'propeller'
+  base_url: 'http://localhost:3100'
+postgres:
+  host: 'localhost'
+  port: 5432
+  database: 'demo'
+vault:
+  base_url: 'http://{vault_host}:{vault_port}'
+  path: 'init/vault/kv1/mount'
+  mount: 'kv-legacy'
+  kv_version: 1
+"
+            )
+            .as_str(),
+        ))
+        .env("VAULT_TOKEN", "root-token")
+        .env("PROPELLER_LOG_LEVEL", "info")
+        .stdout(Stdio::piped())
+        .assert()
+        .success()
+        .stdout(contains(
+            "Successfully initialized Vault path 'init/vault/kv1/mount'",
+        ));
+
+    let vault_secret: VaultSecret = kv1::get(&vault_client, "kv-legacy", "init/vault/kv1/mount")
+        .await
+        .expect("Failed to read Vault secret");
+
+    assert_eq!(vault_secret.postgresql_active_user, "TBD");
+    assert_eq!(vault_secret.postgresql_user_2_password, "TBD");
+}
+
+#[tokio::test]
+#[timeout(60_000)]
+async fn init_vault_aws_secrets_manager() {
+    let localstack_container = localstack_container().await;
+
+    let localstack_host = localstack_container.get_host().await.unwrap();
+    let localstack_port = localstack_container.get_host_port_ipv4(4566).await.unwrap();
+    let endpoint = format!("http://{localstack_host}:{localstack_port}");
+
+    let config_path = write_string_to_tempfile(
+        format!(
+            // language=yaml
+            "
+argo_cd:
+  application: 'propeller'
+  base_url: 'http://localhost:3100'
+postgres:
+  host: 'localhost'
+  port: 5432
+  database: 'demo'
+aws_secrets_manager:
+  secret_id: 'init/aws/secret'
+  region: 'eu-central-2'
+  endpoint: '{endpoint}'
+"
+        )
+        .as_str(),
+    );
+
+    println!("Setup success; invoking propeller...");
+
+    // The second run updates the existing secret by staging a new version
+    for _ in 0..2 {
+        Command::cargo_bin("propeller")
+            .unwrap()
+            .arg("init-vault")
+            .arg("-c")
+            .arg(config_path.as_str())
+            .env("AWS_ACCESS_KEY_ID", "test")
+            .env("AWS_SECRET_ACCESS_KEY", "test")
+            .env("PROPELLER_LOG_LEVEL", "info")
+            .stdout(Stdio::piped())
+            .assert()
+            .success()
+            .stdout(contains(
+                "Successfully initialized AWS secret 'init/aws/secret'",
+            ));
+    }
+
+    let aws_secret = read_aws_secret(endpoint.as_str(), "init/aws/secret").await;
+
+    assert_eq!(aws_secret.postgresql_active_user, "TBD");
+    assert_eq!(aws_secret.postgresql_active_user_password, "TBD");
+    assert_eq!(aws_secret.postgresql_user_1, "TBD");
+    assert_eq!(aws_secret.postgresql_user_1_password, "TBD");
+    assert_eq!(aws_secret.postgresql_user_2, "TBD");
+    assert_eq!(aws_secret.postgresql_user_2_password, "TBD");
+}
+
 #[tokio::test]
 #[timeout(30_000)]
 async fn init_vault_invalid_url() {
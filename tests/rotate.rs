@@ -4,20 +4,33 @@
 // https://opensource.org/licenses/MIT
 
 use assert_cmd::prelude::*;
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::PostParams;
+use kube::Api;
 use ntest::timeout;
 use postgres::NoTls;
+use predicates::prelude::*;
 use predicates::str::contains;
 use reqwest::Client;
+use scylla::client::session::Session;
+use scylla::client::session_builder::SessionBuilder;
 use serde::Deserialize;
 use serde_json::json;
+use std::collections::BTreeMap;
 use std::process::{Command, Stdio};
 use std::time::{Duration, Instant};
+use testcontainers_modules::k3s::K3s;
+use testcontainers_modules::testcontainers::ContainerAsync;
 use tokio::time::sleep;
 use tokio::{join, spawn};
 use utilities::{
-    create_vault_client, deploy_argocd_and_wait_until_ready, get_argocd_access_token,
-    get_kube_client, k3s_container, open_argocd_server_port_forward, postgres_container,
-    read_vault_secret, vault_container, write_string_to_tempfile, VaultSecret,
+    clickhouse_container, cockroachdb_container, create_vault_client,
+    deploy_argocd_and_wait_until_ready, get_argocd_access_token, get_argocd_admin_password,
+    get_kube_client, get_kube_config, k3s_container, localstack_container,
+    open_argocd_server_port_forward, postgres_container, read_aws_secret, read_vault_secret,
+    scylladb_container, vault_container, write_aws_secret, write_string_to_tempfile,
+    yugabytedb_container, VaultSecret,
 };
 use vaultrs::client::VaultClient;
 use vaultrs::kv2;
@@ -85,6 +98,11 @@ async fn rotate_secrets() {
     vault:
       base_url: 'http://{vault_host}:{vault_port}'
       path: 'rotate/secrets'
+      templates:
+        jdbc_url: 'jdbc:postgresql://{{host}}:{{port}}/{{database}}'
+        database_url: 'postgres://{{username}}:{{password}}@{{host}}:{{port}}/{{database}}'
+      fan_out:
+        - path: 'rotate/secrets-fan-out'
 "
             )
             .as_str(),
@@ -106,6 +124,44 @@ async fn rotate_secrets() {
     assert_eq!(vault_secret.postgresql_user_2, "user2");
     assert_ne!(vault_secret.postgresql_user_2_password, "initialpw");
 
+    let raw_secret: serde_json::Value = kv2::read(&vault_client, "secret", "rotate/secrets")
+        .await
+        .expect("Failed to read Vault secret");
+    assert_eq!(
+        raw_secret["jdbc_url"],
+        format!("jdbc:postgresql://{postgres_host}:{postgres_port}/demo")
+    );
+    assert_eq!(
+        raw_secret["database_url"],
+        format!(
+            "postgres://user2:{}@{postgres_host}:{postgres_port}/demo",
+            vault_secret.postgresql_active_user_password
+        )
+    );
+
+    let fan_out_secret: serde_json::Value =
+        kv2::read(&vault_client, "secret", "rotate/secrets-fan-out")
+            .await
+            .expect("Failed to read fan-out Vault secret");
+    assert_eq!(fan_out_secret["postgresql_active_user"], "user2");
+    assert_eq!(
+        fan_out_secret["postgresql_active_user_password"],
+        vault_secret.postgresql_active_user_password
+    );
+    assert!(fan_out_secret.get("postgresql_user_1_password").is_none());
+
+    let custom_metadata = kv2::read_metadata(&vault_client, "secret", "rotate/secrets")
+        .await
+        .expect("Failed to read Vault secret metadata")
+        .custom_metadata
+        .expect("Missing rotation metadata");
+    assert_eq!(custom_metadata["propeller_previous_active_slot"], "user_1");
+    assert_eq!(custom_metadata["propeller_active_slot"], "user_2");
+    assert_eq!(
+        custom_metadata["propeller_argo_cd_application"],
+        "propeller"
+    );
+
     // Expect connection works; password has been changed
     connect_postgres_client(
         postgres_host.as_str(),
@@ -124,19 +180,1268 @@ async fn rotate_secrets() {
     )
     .await;
 
-    // Kill `kubectl port-forward` process
-    stop_sender.send(()).expect("Failed to send stop signal");
+    // Kill `kubectl port-forward` process
+    stop_sender.send(()).expect("Failed to send stop signal");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rotate_application_sync_timeout() {
+    let (k3s_container, postgres_container, vault_container) =
+        join!(k3s_container(), postgres_container(), vault_container());
+
+    let kubectl = get_kube_client(&k3s_container).await;
+
+    let argocd_deployment = deploy_argocd_and_wait_until_ready(&kubectl);
+
+    let (postgres_host, postgres_port, vault_host, vault_port) = join!(
+        postgres_container.get_host(),
+        postgres_container.get_host_port_ipv4(5432),
+        vault_container.get_host(),
+        vault_container.get_host_port_ipv4(8200)
+    );
+
+    let postgres_host = postgres_host.unwrap().to_string();
+    let postgres_port = postgres_port.unwrap().to_string();
+    let vault_host = vault_host.unwrap().to_string();
+    let vault_port = vault_port.unwrap();
+
+    let vault_client = create_vault_client(&vault_host, vault_port);
+    let (_, postgres_client) = join!(
+        reset_vault_secret_path(&vault_client, "rotate/secrets/timeout"),
+        connect_postgres_client(&postgres_host, &postgres_port, "demo", "demo_password",)
+    );
+
+    join!(
+        reset_role_initial_password(&postgres_client, "user1"),
+        reset_role_initial_password(&postgres_client, "user2")
+    );
+
+    // Ensure ArgoCD is ready before proceeding
+    argocd_deployment.await;
+
+    let (argocd_port, stop_sender) = open_argocd_server_port_forward(&kubectl).await;
+
+    let argocd_url = format!("https://localhost:{argocd_port}");
+
+    let argocd_token = get_argocd_access_token(&kubectl, argocd_url.as_str()).await;
+    create_argocd_application(argocd_url.as_str(), argocd_token.as_str()).await;
+
+    println!("Setup success; invoking propeller...");
+
+    Command::cargo_bin("propeller")
+        .unwrap()
+        .arg("rotate")
+        .arg("-c")
+        .arg(write_string_to_tempfile(
+            format!(
+                // language=yaml
+                "
+    argo_cd:
+      application: 'propeller'
+      base_url: 'https://localhost:{argocd_port}'
+      danger_accept_insecure: true
+      sync_timeout_seconds: 5
+    postgres:
+      host: '{postgres_host}'
+      port: {postgres_port}
+      database: 'demo'
+    vault:
+      base_url: 'http://{vault_host}:{vault_port}'
+      path: 'rotate/secrets/timeout'
+"
+            )
+            .as_str(),
+        ))
+        .env("ARGO_CD_TOKEN", argocd_token)
+        .env("VAULT_TOKEN", "root-token")
+        .env("PROPELLER_LOG_LEVEL", "info")
+        .stdout(Stdio::piped())
+        .assert()
+        .failure()
+        .stderr(contains(
+            // The configured sync timeout of 5 seconds is no match for the 10 seconds sleep in the pre-sync hook
+            "Timeout reached while waiting for ArgoCD sync status",
+        ));
+
+    // Kill `kubectl port-forward` process
+    stop_sender.send(()).expect("Failed to send stop signal");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rotate_secrets_argocd_session_and_selector() {
+    let (k3s_container, postgres_container, vault_container) =
+        join!(k3s_container(), postgres_container(), vault_container());
+
+    let kubectl = get_kube_client(&k3s_container).await;
+
+    let argocd_deployment = deploy_argocd_and_wait_until_ready(&kubectl);
+
+    let (postgres_host, postgres_port, vault_host, vault_port) = join!(
+        postgres_container.get_host(),
+        postgres_container.get_host_port_ipv4(5432),
+        vault_container.get_host(),
+        vault_container.get_host_port_ipv4(8200)
+    );
+
+    let postgres_host = postgres_host.unwrap().to_string();
+    let postgres_port = postgres_port.unwrap().to_string();
+    let vault_host = vault_host.unwrap().to_string();
+    let vault_port = vault_port.unwrap();
+
+    let vault_client = create_vault_client(&vault_host, vault_port);
+    let (_, postgres_client) = join!(
+        reset_vault_secret_path(&vault_client, "rotate/secrets/session"),
+        connect_postgres_client(&postgres_host, &postgres_port, "demo", "demo_password",)
+    );
+
+    join!(
+        reset_role_initial_password(&postgres_client, "user1"),
+        reset_role_initial_password(&postgres_client, "user2")
+    );
+
+    // Ensure ArgoCD is ready before proceeding
+    argocd_deployment.await;
+
+    let (argocd_port, stop_sender) = open_argocd_server_port_forward(&kubectl).await;
+
+    let argocd_url = format!("https://localhost:{argocd_port}");
+
+    let argocd_token = get_argocd_access_token(&kubectl, argocd_url.as_str()).await;
+    create_argocd_application(argocd_url.as_str(), argocd_token.as_str()).await;
+
+    let password_path =
+        write_string_to_tempfile(get_argocd_admin_password(&kubectl).await.as_str());
+
+    println!("Setup success; invoking propeller...");
+
+    Command::cargo_bin("propeller")
+        .unwrap()
+        .arg("rotate")
+        .arg("-c")
+        .arg(write_string_to_tempfile(
+            format!(
+                // language=yaml
+                "
+    argo_cd:
+      selector: 'app.kubernetes.io/part-of=propeller'
+      base_url: 'https://localhost:{argocd_port}'
+      danger_accept_insecure: true
+      auth:
+        method: 'session'
+        username: 'admin'
+        password_path: '{password_path}'
+    postgres:
+      host: '{postgres_host}'
+      port: {postgres_port}
+      database: 'demo'
+    vault:
+      base_url: 'http://{vault_host}:{vault_port}'
+      path: 'rotate/secrets/session'
+"
+            )
+            .as_str(),
+        ))
+        .env_remove("ARGO_CD_TOKEN")
+        .env("VAULT_TOKEN", "root-token")
+        .env("PROPELLER_LOG_LEVEL", "info")
+        .stdout(Stdio::piped())
+        .assert()
+        .success()
+        .stderr(contains(
+            "ArgoCD applications matching selector 'app.kubernetes.io/part-of=propeller': [\"propeller\"]",
+        ))
+        .stdout(contains("Successfully rotated all secrets"));
+
+    let vault_secret = read_vault_secret(&vault_client, "rotate/secrets/session").await;
+
+    assert_eq!(vault_secret.postgresql_active_user, "user2");
+
+    // The resolved application is recorded, not the selector
+    let custom_metadata = kv2::read_metadata(&vault_client, "secret", "rotate/secrets/session")
+        .await
+        .expect("Failed to read Vault secret metadata")
+        .custom_metadata
+        .expect("Missing rotation metadata");
+    assert_eq!(
+        custom_metadata["propeller_argo_cd_application"],
+        "propeller"
+    );
+
+    // Kill `kubectl port-forward` process
+    stop_sender.send(()).expect("Failed to send stop signal");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rotate_secrets_argocd_operation_in_progress() {
+    let (k3s_container, postgres_container, vault_container) =
+        join!(k3s_container(), postgres_container(), vault_container());
+
+    let kubectl = get_kube_client(&k3s_container).await;
+
+    let argocd_deployment = deploy_argocd_and_wait_until_ready(&kubectl);
+
+    let (postgres_host, postgres_port, vault_host, vault_port) = join!(
+        postgres_container.get_host(),
+        postgres_container.get_host_port_ipv4(5432),
+        vault_container.get_host(),
+        vault_container.get_host_port_ipv4(8200)
+    );
+
+    let postgres_host = postgres_host.unwrap().to_string();
+    let postgres_port = postgres_port.unwrap().to_string();
+    let vault_host = vault_host.unwrap().to_string();
+    let vault_port = vault_port.unwrap();
+
+    let vault_client = create_vault_client(&vault_host, vault_port);
+    let (_, postgres_client) = join!(
+        reset_vault_secret_path(&vault_client, "rotate/secrets/in/progress"),
+        connect_postgres_client(&postgres_host, &postgres_port, "demo", "demo_password",)
+    );
+
+    join!(
+        reset_role_initial_password(&postgres_client, "user1"),
+        reset_role_initial_password(&postgres_client, "user2")
+    );
+
+    // Ensure ArgoCD is ready before proceeding
+    argocd_deployment.await;
+
+    let (argocd_port, stop_sender) = open_argocd_server_port_forward(&kubectl).await;
+
+    let argocd_url = format!("https://localhost:{argocd_port}");
+
+    let argocd_token = get_argocd_access_token(&kubectl, argocd_url.as_str()).await;
+    create_argocd_application(argocd_url.as_str(), argocd_token.as_str()).await;
+
+    for (policy, expected_log) in [
+        ("wait", "is in progress, waiting for it to finish"),
+        ("terminate", "is in progress, terminating it"),
+    ] {
+        // The pre-sync hook sleeps for 10 seconds, which keeps the operation running while propeller syncs
+        start_argocd_sync(argocd_url.as_str(), argocd_token.as_str()).await;
+
+        println!("Setup success; invoking propeller with policy '{policy}'...");
+
+        Command::cargo_bin("propeller")
+            .unwrap()
+            .arg("rotate")
+            .arg("-c")
+            .arg(write_string_to_tempfile(
+                format!(
+                    // language=yaml
+                    "
+    argo_cd:
+      application: 'propeller'
+      base_url: 'https://localhost:{argocd_port}'
+      danger_accept_insecure: true
+      operation_in_progress: '{policy}'
+      sync_timeout_seconds: 120
+    postgres:
+      host: '{postgres_host}'
+      port: {postgres_port}
+      database: 'demo'
+    vault:
+      base_url: 'http://{vault_host}:{vault_port}'
+      path: 'rotate/secrets/in/progress'
+"
+                )
+                .as_str(),
+            ))
+            .env("ARGO_CD_TOKEN", argocd_token.as_str())
+            .env("VAULT_TOKEN", "root-token")
+            .env("PROPELLER_LOG_LEVEL", "info")
+            .stdout(Stdio::piped())
+            .assert()
+            .success()
+            .stderr(contains(expected_log))
+            .stdout(contains("Successfully rotated all secrets"));
+    }
+
+    // Rotated twice, so the first user is active again
+    let vault_secret = read_vault_secret(&vault_client, "rotate/secrets/in/progress").await;
+
+    assert_eq!(vault_secret.postgresql_active_user, "user1");
+
+    // Kill `kubectl port-forward` process
+    stop_sender.send(()).expect("Failed to send stop signal");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rotate_secrets_argocd_multiple_applications() {
+    let (k3s_container, postgres_container, vault_container) =
+        join!(k3s_container(), postgres_container(), vault_container());
+
+    let kubectl = get_kube_client(&k3s_container).await;
+
+    let argocd_deployment = deploy_argocd_and_wait_until_ready(&kubectl);
+
+    let (postgres_host, postgres_port, vault_host, vault_port) = join!(
+        postgres_container.get_host(),
+        postgres_container.get_host_port_ipv4(5432),
+        vault_container.get_host(),
+        vault_container.get_host_port_ipv4(8200)
+    );
+
+    let postgres_host = postgres_host.unwrap().to_string();
+    let postgres_port = postgres_port.unwrap().to_string();
+    let vault_host = vault_host.unwrap().to_string();
+    let vault_port = vault_port.unwrap();
+
+    let vault_client = create_vault_client(&vault_host, vault_port);
+    let (_, postgres_client) = join!(
+        reset_vault_secret_path(&vault_client, "rotate/secrets/multiple"),
+        connect_postgres_client(&postgres_host, &postgres_port, "demo", "demo_password",)
+    );
+
+    join!(
+        reset_role_initial_password(&postgres_client, "user1"),
+        reset_role_initial_password(&postgres_client, "user2")
+    );
+
+    // Ensure ArgoCD is ready before proceeding
+    argocd_deployment.await;
+
+    let (argocd_port, stop_sender) = open_argocd_server_port_forward(&kubectl).await;
+
+    let argocd_url = format!("https://localhost:{argocd_port}");
+
+    let argocd_token = get_argocd_access_token(&kubectl, argocd_url.as_str()).await;
+    join!(
+        create_argocd_application(argocd_url.as_str(), argocd_token.as_str()),
+        create_named_argocd_application(
+            argocd_url.as_str(),
+            argocd_token.as_str(),
+            "propeller-secondary",
+            "propeller-secondary"
+        )
+    );
+
+    for sync_order in ["parallel", "sequential"] {
+        println!("Setup success; invoking propeller with sync order '{sync_order}'...");
+
+        Command::cargo_bin("propeller")
+            .unwrap()
+            .arg("rotate")
+            .arg("-c")
+            .arg(write_string_to_tempfile(
+                format!(
+                    // language=yaml
+                    "
+    argo_cd:
+      applications:
+        - 'propeller'
+        - 'propeller-secondary'
+      base_url: 'https://localhost:{argocd_port}'
+      danger_accept_insecure: true
+      sync_order: '{sync_order}'
+      sync_timeout_seconds: 120
+    postgres:
+      host: '{postgres_host}'
+      port: {postgres_port}
+      database: 'demo'
+    vault:
+      base_url: 'http://{vault_host}:{vault_port}'
+      path: 'rotate/secrets/multiple'
+"
+                )
+                .as_str(),
+            ))
+            .env("ARGO_CD_TOKEN", argocd_token.as_str())
+            .env("VAULT_TOKEN", "root-token")
+            .env("PROPELLER_LOG_LEVEL", "info")
+            .stdout(Stdio::piped())
+            .assert()
+            .success()
+            .stderr(
+                contains("Waiting for rollout of ArgoCD application 'propeller'").and(contains(
+                    "Waiting for rollout of ArgoCD application 'propeller-secondary'",
+                )),
+            )
+            .stdout(contains("Successfully rotated all secrets"));
+    }
+
+    // Rotated twice, so the first user is active again
+    let vault_secret = read_vault_secret(&vault_client, "rotate/secrets/multiple").await;
+
+    assert_eq!(vault_secret.postgresql_active_user, "user1");
+
+    // Kill `kubectl port-forward` process
+    stop_sender.send(()).expect("Failed to send stop signal");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rotate_secrets_argocd_hard_refresh_unchanged_manifests() {
+    let (k3s_container, postgres_container, vault_container) =
+        join!(k3s_container(), postgres_container(), vault_container());
+
+    let kubectl = get_kube_client(&k3s_container).await;
+
+    let argocd_deployment = deploy_argocd_and_wait_until_ready(&kubectl);
+
+    let (postgres_host, postgres_port, vault_host, vault_port) = join!(
+        postgres_container.get_host(),
+        postgres_container.get_host_port_ipv4(5432),
+        vault_container.get_host(),
+        vault_container.get_host_port_ipv4(8200)
+    );
+
+    let postgres_host = postgres_host.unwrap().to_string();
+    let postgres_port = postgres_port.unwrap().to_string();
+    let vault_host = vault_host.unwrap().to_string();
+    let vault_port = vault_port.unwrap();
+
+    let vault_client = create_vault_client(&vault_host, vault_port);
+    let (_, postgres_client) = join!(
+        reset_vault_secret_path(&vault_client, "rotate/secrets/hard/refresh"),
+        connect_postgres_client(&postgres_host, &postgres_port, "demo", "demo_password",)
+    );
+
+    join!(
+        reset_role_initial_password(&postgres_client, "user1"),
+        reset_role_initial_password(&postgres_client, "user2")
+    );
+
+    // Ensure ArgoCD is ready before proceeding
+    argocd_deployment.await;
+
+    let (argocd_port, stop_sender) = open_argocd_server_port_forward(&kubectl).await;
+
+    let argocd_url = format!("https://localhost:{argocd_port}");
+
+    let argocd_token = get_argocd_access_token(&kubectl, argocd_url.as_str()).await;
+    create_argocd_application(argocd_url.as_str(), argocd_token.as_str()).await;
+
+    println!("Setup success; invoking propeller...");
+
+    Command::cargo_bin("propeller")
+        .unwrap()
+        .arg("rotate")
+        .arg("-c")
+        .arg(write_string_to_tempfile(
+            format!(
+                // language=yaml
+                "
+    argo_cd:
+      application: 'propeller'
+      base_url: 'https://localhost:{argocd_port}'
+      danger_accept_insecure: true
+      hard_refresh: true
+      wait_for_manifest_change:
+        - 'propeller'
+      sync_timeout_seconds: 20
+    postgres:
+      host: '{postgres_host}'
+      port: {postgres_port}
+      database: 'demo'
+    vault:
+      base_url: 'http://{vault_host}:{vault_port}'
+      path: 'rotate/secrets/hard/refresh'
+"
+            )
+            .as_str(),
+        ))
+        .env("ARGO_CD_TOKEN", argocd_token)
+        .env("VAULT_TOKEN", "root-token")
+        .env("PROPELLER_LOG_LEVEL", "info")
+        .stdout(Stdio::piped())
+        .assert()
+        .success()
+        .stderr(contains(
+            // The manifests of the test application are not rendered from Vault
+            "Manifests of ArgoCD application 'propeller' did not change after the hard refresh, syncing anyway",
+        ))
+        .stdout(contains("Successfully rotated all secrets"));
+
+    let vault_secret = read_vault_secret(&vault_client, "rotate/secrets/hard/refresh").await;
+
+    assert_eq!(vault_secret.postgresql_active_user, "user2");
+
+    // Kill `kubectl port-forward` process
+    stop_sender.send(()).expect("Failed to send stop signal");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rotate_secrets_kubernetes() {
+    let (k3s_container, postgres_container) = join!(k3s_container(), postgres_container());
+
+    let kubectl = get_kube_client(&k3s_container).await;
+    let kubeconfig_path = write_string_to_tempfile(
+        serde_yaml::to_string(&get_kube_config(&k3s_container).await)
+            .expect("Failed to serialize kube config")
+            .as_str(),
+    );
+
+    let argocd_deployment = deploy_argocd_and_wait_until_ready(&kubectl);
+
+    let (postgres_host, postgres_port) = join!(
+        postgres_container.get_host(),
+        postgres_container.get_host_port_ipv4(5432)
+    );
+
+    let postgres_host = postgres_host.unwrap().to_string();
+    let postgres_port = postgres_port.unwrap().to_string();
+
+    let secrets: Api<Secret> = Api::namespaced(kubectl.clone(), "default");
+    let (_, postgres_client) = join!(
+        reset_kubernetes_secret(&secrets, "propeller-database"),
+        connect_postgres_client(&postgres_host, &postgres_port, "demo", "demo_password",)
+    );
+
+    join!(
+        reset_role_initial_password(&postgres_client, "user1"),
+        reset_role_initial_password(&postgres_client, "user2")
+    );
+
+    // Ensure ArgoCD is ready before proceeding
+    argocd_deployment.await;
+
+    let (argocd_port, stop_sender) = open_argocd_server_port_forward(&kubectl).await;
+
+    let argocd_url = format!("https://localhost:{argocd_port}");
+
+    let argocd_token = get_argocd_access_token(&kubectl, argocd_url.as_str()).await;
+    create_argocd_application(argocd_url.as_str(), argocd_token.as_str()).await;
+
+    println!("Setup success; invoking propeller...");
+
+    Command::cargo_bin("propeller")
+        .unwrap()
+        .arg("rotate")
+        .arg("-c")
+        .arg(write_string_to_tempfile(
+            format!(
+                // language=yaml
+                "
+    argo_cd:
+      application: 'propeller'
+      base_url: 'https://localhost:{argocd_port}'
+      danger_accept_insecure: true
+    kubernetes:
+      secret: 'propeller-database'
+      namespace: 'default'
+      kubeconfig_path: '{kubeconfig_path}'
+    postgres:
+      host: '{postgres_host}'
+      port: {postgres_port}
+      database: 'demo'
+"
+            )
+            .as_str(),
+        ))
+        .env("ARGO_CD_TOKEN", argocd_token)
+        .env("PROPELLER_LOG_LEVEL", "debug,rustify=off")
+        .stdout(Stdio::piped())
+        .assert()
+        .success()
+        .stdout(contains("Successfully rotated all secrets"));
+
+    let secret_data = secrets
+        .get("propeller-database")
+        .await
+        .expect("Failed to read Kubernetes Secret")
+        .data
+        .expect("Missing Kubernetes Secret data");
+    let read_key = |key: &str| String::from_utf8(secret_data[key].0.clone()).unwrap();
+
+    assert_eq!(read_key("postgresql_active_user"), "user2");
+    assert_eq!(read_key("postgresql_user_1"), "user1");
+    assert_ne!(read_key("postgresql_user_1_password"), "initialpw");
+    assert_eq!(read_key("postgresql_user_2"), "user2");
+    assert_ne!(read_key("postgresql_user_2_password"), "initialpw");
+    assert_eq!(
+        read_key("postgresql_active_user_password"),
+        read_key("postgresql_user_2_password")
+    );
+
+    // Expect connection works; password has been changed
+    connect_postgres_client(
+        postgres_host.as_str(),
+        postgres_port.as_str(),
+        "user1",
+        read_key("postgresql_user_1_password").as_str(),
+    )
+    .await;
+
+    // Kill `kubectl port-forward` process
+    stop_sender.send(()).expect("Failed to send stop signal");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rotate_secrets_aws_secrets_manager() {
+    let (k3s_container, postgres_container, localstack_container) = join!(
+        k3s_container(),
+        postgres_container(),
+        localstack_container()
+    );
+
+    let kubectl = get_kube_client(&k3s_container).await;
+
+    let argocd_deployment = deploy_argocd_and_wait_until_ready(&kubectl);
+
+    let (postgres_host, postgres_port, localstack_host, localstack_port) = join!(
+        postgres_container.get_host(),
+        postgres_container.get_host_port_ipv4(5432),
+        localstack_container.get_host(),
+        localstack_container.get_host_port_ipv4(4566)
+    );
+
+    let postgres_host = postgres_host.unwrap().to_string();
+    let postgres_port = postgres_port.unwrap().to_string();
+    let endpoint = format!(
+        "http://{}:{}",
+        localstack_host.unwrap(),
+        localstack_port.unwrap()
+    );
+
+    let initial_secret = VaultSecret {
+        postgresql_active_user: "user1".to_string(),
+        postgresql_active_user_password: "initialpw".to_string(),
+        postgresql_user_1: "user1".to_string(),
+        postgresql_user_1_password: "initialpw".to_string(),
+        postgresql_user_2: "user2".to_string(),
+        postgresql_user_2_password: "initialpw".to_string(),
+    };
+    let (_, postgres_client) = join!(
+        write_aws_secret(&endpoint, "rotate/aws/secret", &initial_secret),
+        connect_postgres_client(&postgres_host, &postgres_port, "demo", "demo_password",)
+    );
+
+    join!(
+        reset_role_initial_password(&postgres_client, "user1"),
+        reset_role_initial_password(&postgres_client, "user2")
+    );
+
+    // Ensure ArgoCD is ready before proceeding
+    argocd_deployment.await;
+
+    let (argocd_port, stop_sender) = open_argocd_server_port_forward(&kubectl).await;
+
+    let argocd_url = format!("https://localhost:{argocd_port}");
+
+    let argocd_token = get_argocd_access_token(&kubectl, argocd_url.as_str()).await;
+    create_argocd_application(argocd_url.as_str(), argocd_token.as_str()).await;
+
+    println!("Setup success; invoking propeller...");
+
+    Command::cargo_bin("propeller")
+        .unwrap()
+        .arg("rotate")
+        .arg("-c")
+        .arg(write_string_to_tempfile(
+            format!(
+                // language=yaml
+                "
+    argo_cd:
+      application: 'propeller'
+      base_url: 'https://localhost:{argocd_port}'
+      danger_accept_insecure: true
+    aws_secrets_manager:
+      secret_id: 'rotate/aws/secret'
+      region: 'eu-central-2'
+      endpoint: '{endpoint}'
+    postgres:
+      host: '{postgres_host}'
+      port: {postgres_port}
+      database: 'demo'
+"
+            )
+            .as_str(),
+        ))
+        .env("ARGO_CD_TOKEN", argocd_token)
+        .env("AWS_ACCESS_KEY_ID", "test")
+        .env("AWS_SECRET_ACCESS_KEY", "test")
+        .env("PROPELLER_LOG_LEVEL", "debug,rustify=off")
+        .stdout(Stdio::piped())
+        .assert()
+        .success()
+        .stdout(contains("Successfully rotated all secrets"));
+
+    let aws_secret = read_aws_secret(&endpoint, "rotate/aws/secret").await;
+
+    assert_eq!(aws_secret.postgresql_active_user, "user2");
+    assert_eq!(aws_secret.postgresql_user_1, "user1");
+    assert_ne!(aws_secret.postgresql_user_1_password, "initialpw");
+    assert_eq!(aws_secret.postgresql_user_2, "user2");
+    assert_ne!(aws_secret.postgresql_user_2_password, "initialpw");
+    assert_eq!(
+        aws_secret.postgresql_active_user_password,
+        aws_secret.postgresql_user_2_password
+    );
+
+    // Expect connection works; password has been changed
+    connect_postgres_client(
+        postgres_host.as_str(),
+        postgres_port.as_str(),
+        "user1",
+        aws_secret.postgresql_user_1_password.as_str(),
+    )
+    .await;
+
+    // Kill `kubectl port-forward` process
+    stop_sender.send(()).expect("Failed to send stop signal");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rotate_secrets_cockroachdb() {
+    let (k3s_container, database_container, vault_container) =
+        join!(k3s_container(), cockroachdb_container(), vault_container());
+
+    let (database_host, database_port, vault_host, vault_port) = join!(
+        database_container.get_host(),
+        database_container.get_host_port_ipv4(26257),
+        vault_container.get_host(),
+        vault_container.get_host_port_ipv4(8200)
+    );
+
+    let database_host = database_host.unwrap().to_string();
+    let database_port = database_port.unwrap().to_string();
+    let vault_host = vault_host.unwrap().to_string();
+    let vault_port = vault_port.unwrap();
+
+    let vault_client = create_vault_client(&vault_host, vault_port);
+    let (_, database_client) = join!(
+        reset_vault_secret_path(&vault_client, "rotate/secrets/cockroachdb"),
+        await_postgres_client(
+            &database_host,
+            &database_port,
+            "demo",
+            "demo",
+            "demo_password"
+        )
+    );
+    join!(
+        reset_role_initial_password(&database_client, "user1"),
+        reset_role_initial_password(&database_client, "user2")
+    );
+
+    let (vault_secret, _) = rotate_secrets_with_database_target(
+        &k3s_container,
+        &vault_client,
+        &format!("http://{vault_host}:{vault_port}"),
+        "rotate/secrets/cockroachdb",
+        &format!(
+            // language=yaml
+            "
+    postgres:
+      host: '{database_host}'
+      port: {database_port}
+      database: 'demo'
+      dialect: 'cockroachdb'
+"
+        ),
+    )
+    .await;
+
+    // Expect connection works; password has been changed
+    connect_postgres_client(
+        database_host.as_str(),
+        database_port.as_str(),
+        "user1",
+        vault_secret.postgresql_user_1_password.as_str(),
+    )
+    .await;
+
+    // Expect connection works; password has been changed
+    connect_postgres_client(
+        database_host.as_str(),
+        database_port.as_str(),
+        "user2",
+        vault_secret.postgresql_user_2_password.as_str(),
+    )
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rotate_secrets_yugabytedb() {
+    let (k3s_container, database_container, vault_container) =
+        join!(k3s_container(), yugabytedb_container(), vault_container());
+
+    let (database_host, database_port, vault_host, vault_port) = join!(
+        database_container.get_host(),
+        database_container.get_host_port_ipv4(5433),
+        vault_container.get_host(),
+        vault_container.get_host_port_ipv4(8200)
+    );
+
+    let database_host = database_host.unwrap().to_string();
+    let database_port = database_port.unwrap().to_string();
+    let vault_host = vault_host.unwrap().to_string();
+    let vault_port = vault_port.unwrap();
+
+    let vault_client = create_vault_client(&vault_host, vault_port);
+    let (_, database_client) = join!(
+        reset_vault_secret_path(&vault_client, "rotate/secrets/yugabytedb"),
+        await_postgres_client(
+            &database_host,
+            &database_port,
+            "yugabyte",
+            "yugabyte",
+            "yugabyte"
+        )
+    );
+
+    // YugabyteDB only ships with the 'yugabyte' database
+    database_client
+        .execute("CREATE DATABASE demo", &[])
+        .await
+        .expect("Failed to create 'demo' database");
+
+    join!(
+        reset_role_initial_password(&database_client, "user1"),
+        reset_role_initial_password(&database_client, "user2")
+    );
+
+    let (vault_secret, _) = rotate_secrets_with_database_target(
+        &k3s_container,
+        &vault_client,
+        &format!("http://{vault_host}:{vault_port}"),
+        "rotate/secrets/yugabytedb",
+        &format!(
+            // language=yaml
+            "
+    postgres:
+      host: '{database_host}'
+      port: {database_port}
+      database: 'demo'
+      dialect: 'yugabytedb'
+"
+        ),
+    )
+    .await;
+
+    // Expect connection works; password has been changed
+    connect_postgres_client(
+        database_host.as_str(),
+        database_port.as_str(),
+        "user1",
+        vault_secret.postgresql_user_1_password.as_str(),
+    )
+    .await;
+
+    // Expect connection works; password has been changed
+    connect_postgres_client(
+        database_host.as_str(),
+        database_port.as_str(),
+        "user2",
+        vault_secret.postgresql_user_2_password.as_str(),
+    )
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rotate_secrets_clickhouse() {
+    let (k3s_container, clickhouse_container, vault_container) =
+        join!(k3s_container(), clickhouse_container(), vault_container());
+
+    let (clickhouse_host, clickhouse_port, vault_host, vault_port) = join!(
+        clickhouse_container.get_host(),
+        clickhouse_container.get_host_port_ipv4(8123),
+        vault_container.get_host(),
+        vault_container.get_host_port_ipv4(8200)
+    );
+
+    let clickhouse_url = format!(
+        "http://{}:{}",
+        clickhouse_host.unwrap(),
+        clickhouse_port.unwrap()
+    );
+    let vault_host = vault_host.unwrap().to_string();
+    let vault_port = vault_port.unwrap();
+
+    let vault_client = create_vault_client(&vault_host, vault_port);
+    reset_vault_secret_path(&vault_client, "rotate/secrets/clickhouse").await;
+
+    join!(
+        reset_clickhouse_user_initial_password(&clickhouse_url, "user1"),
+        reset_clickhouse_user_initial_password(&clickhouse_url, "user2")
+    );
+
+    let (vault_secret, _) = rotate_secrets_with_database_target(
+        &k3s_container,
+        &vault_client,
+        &format!("http://{vault_host}:{vault_port}"),
+        "rotate/secrets/clickhouse",
+        &format!(
+            // language=yaml
+            "
+    clickhouse:
+      base_url: '{clickhouse_url}'
+"
+        ),
+    )
+    .await;
+
+    // Expect login works; password has been changed
+    execute_clickhouse_query(
+        &clickhouse_url,
+        "user1",
+        vault_secret.postgresql_user_1_password.as_str(),
+        "SELECT 1",
+    )
+    .await;
+
+    // Expect login works; password has been changed
+    execute_clickhouse_query(
+        &clickhouse_url,
+        "user2",
+        vault_secret.postgresql_user_2_password.as_str(),
+        "SELECT 1",
+    )
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rotate_secrets_cassandra() {
+    let (k3s_container, scylladb_container, vault_container) =
+        join!(k3s_container(), scylladb_container(), vault_container());
+
+    let (scylladb_host, scylladb_port, vault_host, vault_port) = join!(
+        scylladb_container.get_host(),
+        scylladb_container.get_host_port_ipv4(9042),
+        vault_container.get_host(),
+        vault_container.get_host_port_ipv4(8200)
+    );
+
+    let contact_point = format!("{}:{}", scylladb_host.unwrap(), scylladb_port.unwrap());
+    let vault_host = vault_host.unwrap().to_string();
+    let vault_port = vault_port.unwrap();
+
+    let vault_client = create_vault_client(&vault_host, vault_port);
+    let (_, cassandra_session) = join!(
+        reset_vault_secret_path(&vault_client, "rotate/secrets/cassandra"),
+        await_cassandra_session(&contact_point, "cassandra", "cassandra")
+    );
+
+    join!(
+        reset_cassandra_role_initial_password(&cassandra_session, "user1"),
+        reset_cassandra_role_initial_password(&cassandra_session, "user2")
+    );
+
+    let (vault_secret, _) = rotate_secrets_with_database_target(
+        &k3s_container,
+        &vault_client,
+        &format!("http://{vault_host}:{vault_port}"),
+        "rotate/secrets/cassandra",
+        &format!(
+            // language=yaml
+            "
+    cassandra:
+      contact_points:
+        - '{contact_point}'
+"
+        ),
+    )
+    .await;
+
+    // Expect login works; password has been changed
+    SessionBuilder::new()
+        .known_node(&contact_point)
+        .user("user1", vault_secret.postgresql_user_1_password.as_str())
+        .build()
+        .await
+        .expect("Failed to login as 'user1'");
+
+    // Expect login works; password has been changed
+    SessionBuilder::new()
+        .known_node(&contact_point)
+        .user("user2", vault_secret.postgresql_user_2_password.as_str())
+        .build()
+        .await
+        .expect("Failed to login as 'user2'");
+}
+
+#[tokio::test]
+#[timeout(30_000)]
+async fn rotate_missing_vault_token() {
+    Command::cargo_bin("propeller")
+        .unwrap()
+        .arg("rotate")
+        .arg("-c")
+        .arg(write_string_to_tempfile(
+            format!(
+                // language=yaml
+                "
+argo_cd:
+  application: 'propeller'
+  base_url: 'http://localhost:8080'
+postgres:
+  host: 'localhost'
+  port: 5432
+  database: 'demo'
+vault:
+  base_url: 'http://localhost:8200'
+  path: 'rotate/non/existing/path'
+"
+            )
+            .as_str(),
+        ))
+        .env("ARGO_CD_TOKEN", "argocd-token")
+        .env("PROPELLER_LOG_LEVEL", "info")
+        .stdout(Stdio::piped())
+        .assert()
+        .failure()
+        .stderr(contains("Missing VAULT_TOKEN environment variable"));
+}
+
+#[tokio::test]
+#[timeout(30_000)]
+async fn rotate_missing_argocd_credentials() {
+    Command::cargo_bin("propeller")
+        .unwrap()
+        .arg("rotate")
+        .arg("-c")
+        .arg(write_string_to_tempfile(
+            // language=yaml
+            "
+argo_cd:
+  application: 'propeller'
+  base_url: 'http://localhost:8080'
+postgres:
+  host: 'localhost'
+  port: 5432
+  database: 'demo'
+vault:
+  base_url: 'http://localhost:8200'
+  path: 'rotate/non/existing/path'
+",
+        ))
+        .env_remove("ARGO_CD_TOKEN")
+        .env("VAULT_TOKEN", "root-token")
+        .env("PROPELLER_LOG_LEVEL", "info")
+        .stdout(Stdio::piped())
+        .assert()
+        .failure()
+        .stderr(contains("Missing ArgoCD credentials"));
+}
+
+#[tokio::test]
+#[timeout(30_000)]
+async fn rotate_invalid_initialized_secret() {
+    let vault_container = vault_container().await;
+
+    let vault_host = vault_container.get_host().await.unwrap();
+    let vault_port = vault_container.get_host_port_ipv4(8200).await.unwrap();
+
+    let vault_client = create_vault_client(vault_host.to_string().as_str(), vault_port);
+    create_invalid_vault_secret_path(&vault_client, "rotate/invalid/initialized/secret").await;
+
+    println!("Setup success; invoking propeller...");
+
+    Command::cargo_bin("propeller")
+        .unwrap()
+        .arg("rotate")
+        .arg("-c")
+        .arg(write_string_to_tempfile(
+            format!(
+                // language=yaml
+                "
+argo_cd:
+  application: 'propeller'
+  base_url: 'http://localhost:8080'
+postgres:
+  host: 'localhost'
+  port: 5432
+  database: 'demo'
+vault:
+  base_url: 'http://{vault_host}:{vault_port}'
+  path: 'rotate/invalid/initialized/secret'
+"
+            )
+            .as_str(),
+        ))
+        .env("ARGO_CD_TOKEN", "argocd-token")
+        .env("VAULT_TOKEN", "root-token")
+        .env("PROPELLER_LOG_LEVEL", "info")
+        .stdout(Stdio::piped())
+        .assert()
+        .failure()
+        .stderr(contains(
+            "Failed to detect active user - did neither match user 1 nor 2",
+        ));
+}
+
+#[tokio::test]
+#[timeout(30_000)]
+async fn rotate_non_existing_secret() {
+    let vault_container = vault_container().await;
+
+    let vault_host = vault_container.get_host().await.unwrap();
+    let vault_port = vault_container.get_host_port_ipv4(8200).await.unwrap();
+
+    println!("Setup success; invoking propeller...");
+
+    Command::cargo_bin("propeller")
+        .unwrap()
+        .arg("rotate")
+        .arg("-c")
+        .arg(write_string_to_tempfile(
+            format!(
+                // language=yaml
+                "
+argo_cd:
+  application: 'propeller'
+  base_url: 'http://localhost:8080'
+postgres:
+  host: 'localhost'
+  port: 5432
+  database: 'demo'
+vault:
+  base_url: 'http://{vault_host}:{vault_port}'
+  path: 'rotate/non/existing/path'
+"
+            )
+            .as_str(),
+        ))
+        .env("ARGO_CD_TOKEN", "argocd-token")
+        .env("VAULT_TOKEN", "root-token")
+        .env("PROPELLER_LOG_LEVEL", "info")
+        .stdout(Stdio::piped())
+        .assert()
+        .failure()
+        .stderr(contains(
+            "Failed to read path 'rotate/non/existing/path' - did you init Vault?",
+        ));
+}
+
+#[tokio::test]
+#[timeout(30_000)]
+async fn rotate_locked_secret() {
+    let vault_container = vault_container().await;
+
+    let vault_host = vault_container.get_host().await.unwrap();
+    let vault_port = vault_container.get_host_port_ipv4(8200).await.unwrap();
+
+    let vault_client = create_vault_client(vault_host.to_string().as_str(), vault_port);
+    kv2::set(
+        &vault_client,
+        "secret",
+        "rotate/locked/secret.lock",
+        &json!({ "owner": "other-run", "expires_at": 4_102_444_800u64 }),
+    )
+    .await
+    .expect("Failed to write Vault lock");
+
+    println!("Setup success; invoking propeller...");
+
+    Command::cargo_bin("propeller")
+        .unwrap()
+        .arg("rotate")
+        .arg("-c")
+        .arg(write_string_to_tempfile(
+            format!(
+                // language=yaml
+                "
+argo_cd:
+  application: 'propeller'
+  base_url: 'http://localhost:8080'
+lock:
+  backend: 'vault'
+postgres:
+  host: 'localhost'
+  port: 5432
+  database: 'demo'
+vault:
+  base_url: 'http://{vault_host}:{vault_port}'
+  path: 'rotate/locked/secret'
+"
+            )
+            .as_str(),
+        ))
+        .env("ARGO_CD_TOKEN", "argocd-token")
+        .env("VAULT_TOKEN", "root-token")
+        .env("PROPELLER_LOG_LEVEL", "info")
+        .stdout(Stdio::piped())
+        .assert()
+        .failure()
+        .stderr(contains(
+            "Failed to acquire Vault lock 'rotate/locked/secret.lock': held by 'other-run'",
+        ));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[timeout(120_000)]
+async fn rotate_locked_postgres() {
+    let (postgres_container, vault_container) = join!(postgres_container(), vault_container());
+
+    let (postgres_host, postgres_port, vault_host, vault_port) = join!(
+        postgres_container.get_host(),
+        postgres_container.get_host_port_ipv4(5432),
+        vault_container.get_host(),
+        vault_container.get_host_port_ipv4(8200)
+    );
+
+    let postgres_host = postgres_host.unwrap().to_string();
+    let postgres_port = postgres_port.unwrap().to_string();
+    let vault_host = vault_host.unwrap().to_string();
+    let vault_port = vault_port.unwrap();
+
+    let vault_client = create_vault_client(&vault_host, vault_port);
+    let (_, postgres_client) = join!(
+        reset_vault_secret_path(&vault_client, "rotate/postgres-locked/secret"),
+        connect_postgres_client(&postgres_host, &postgres_port, "demo", "demo_password",)
+    );
+
+    join!(
+        reset_role_initial_password(&postgres_client, "user1"),
+        reset_role_initial_password(&postgres_client, "user2")
+    );
+
+    // Another run holding the lock, "prop" in ASCII being the first key used by propeller
+    postgres_client
+        .execute(
+            "SELECT pg_advisory_lock(1886547824, hashtext('propeller:rotate/postgres-locked/secret'))",
+            &[],
+        )
+        .await
+        .expect("Failed to take PostgreSQL lock");
+
+    println!("Setup success; invoking propeller...");
+
+    Command::cargo_bin("propeller")
+        .unwrap()
+        .arg("rotate")
+        .arg("-c")
+        .arg(write_string_to_tempfile(
+            format!(
+                // language=yaml
+                "
+argo_cd:
+  application: 'propeller'
+  base_url: 'http://localhost:8080'
+lock:
+  backend: 'postgres'
+postgres:
+  host: '{postgres_host}'
+  port: {postgres_port}
+  database: 'demo'
+vault:
+  base_url: 'http://{vault_host}:{vault_port}'
+  path: 'rotate/postgres-locked/secret'
+"
+            )
+            .as_str(),
+        ))
+        .env("ARGO_CD_TOKEN", "argocd-token")
+        .env("VAULT_TOKEN", "root-token")
+        .env("PROPELLER_LOG_LEVEL", "info")
+        .stdout(Stdio::piped())
+        .assert()
+        .failure()
+        .stderr(contains(
+            "Failed to acquire PostgreSQL lock 'propeller:rotate/postgres-locked/secret': another rotation is in progress",
+        ));
+
+    let vault_secret = read_vault_secret(&vault_client, "rotate/postgres-locked/secret").await;
+
+    assert_eq!(vault_secret.postgresql_active_user, "user1");
+    assert_eq!(vault_secret.postgresql_user_2_password, "initialpw");
 }
 
 #[tokio::test(flavor = "multi_thread")]
-async fn rotate_application_sync_timeout() {
+async fn rotate_secrets_postgres_lock() {
     let (k3s_container, postgres_container, vault_container) =
         join!(k3s_container(), postgres_container(), vault_container());
 
-    let kubectl = get_kube_client(&k3s_container).await;
-
-    let argocd_deployment = deploy_argocd_and_wait_until_ready(&kubectl);
-
     let (postgres_host, postgres_port, vault_host, vault_port) = join!(
         postgres_container.get_host(),
         postgres_container.get_host_port_ipv4(5432),
@@ -151,7 +1456,7 @@ async fn rotate_application_sync_timeout() {
 
     let vault_client = create_vault_client(&vault_host, vault_port);
     let (_, postgres_client) = join!(
-        reset_vault_secret_path(&vault_client, "rotate/secrets/timeout"),
+        reset_vault_secret_path(&vault_client, "rotate/secrets/postgres-lock"),
         connect_postgres_client(&postgres_host, &postgres_port, "demo", "demo_password",)
     );
 
@@ -160,60 +1465,42 @@ async fn rotate_application_sync_timeout() {
         reset_role_initial_password(&postgres_client, "user2")
     );
 
-    // Ensure ArgoCD is ready before proceeding
-    argocd_deployment.await;
-
-    let (argocd_port, stop_sender) = open_argocd_server_port_forward(&kubectl).await;
-
-    let argocd_url = format!("https://localhost:{argocd_port}");
-
-    let argocd_token = get_argocd_access_token(&kubectl, argocd_url.as_str()).await;
-    create_argocd_application(argocd_url.as_str(), argocd_token.as_str()).await;
-
-    println!("Setup success; invoking propeller...");
-
-    Command::cargo_bin("propeller")
-        .unwrap()
-        .arg("rotate")
-        .arg("-c")
-        .arg(write_string_to_tempfile(
-            format!(
-                // language=yaml
-                "
-    argo_cd:
-      application: 'propeller'
-      base_url: 'https://localhost:{argocd_port}'
-      danger_accept_insecure: true
-      sync_timeout_seconds: 5
+    let (_, stderr) = rotate_secrets_with_database_target(
+        &k3s_container,
+        &vault_client,
+        &format!("http://{vault_host}:{vault_port}"),
+        "rotate/secrets/postgres-lock",
+        &format!(
+            // language=yaml
+            "
+    lock:
+      backend: 'postgres'
     postgres:
       host: '{postgres_host}'
       port: {postgres_port}
       database: 'demo'
-    vault:
-      base_url: 'http://{vault_host}:{vault_port}'
-      path: 'rotate/secrets/timeout'
 "
-            )
-            .as_str(),
-        ))
-        .env("ARGO_CD_TOKEN", argocd_token)
-        .env("VAULT_TOKEN", "root-token")
-        .env("PROPELLER_LOG_LEVEL", "info")
-        .stdout(Stdio::piped())
-        .assert()
-        .failure()
-        .stderr(contains(
-            // The configured sync timeout of 5 seconds is no match for the 10 seconds sleep in the pre-sync hook
-            "Timeout reached while waiting for ArgoCD sync status",
-        ));
+        ),
+    )
+    .await;
 
-    // Kill `kubectl port-forward` process
-    stop_sender.send(()).expect("Failed to send stop signal");
+    assert!(stderr.contains("Released PostgreSQL lock 'propeller:rotate/secrets/postgres-lock'"));
+    // The session holding the lock must not count as an open session of the previously active user
+    assert!(!stderr.contains("open session"));
 }
 
 #[tokio::test]
 #[timeout(30_000)]
-async fn rotate_missing_vault_token() {
+async fn rotate_failure_releases_vault_lock() {
+    let vault_container = vault_container().await;
+
+    let vault_host = vault_container.get_host().await.unwrap();
+    let vault_port = vault_container.get_host_port_ipv4(8200).await.unwrap();
+
+    let vault_client = create_vault_client(vault_host.to_string().as_str(), vault_port);
+
+    println!("Setup success; invoking propeller...");
+
     Command::cargo_bin("propeller")
         .unwrap()
         .arg("rotate")
@@ -225,37 +1512,70 @@ async fn rotate_missing_vault_token() {
 argo_cd:
   application: 'propeller'
   base_url: 'http://localhost:8080'
+lock:
+  backend: 'vault'
 postgres:
   host: 'localhost'
   port: 5432
   database: 'demo'
 vault:
-  base_url: 'http://localhost:8200'
-  path: 'rotate/non/existing/path'
+  base_url: 'http://{vault_host}:{vault_port}'
+  path: 'rotate/released/secret'
 "
             )
             .as_str(),
         ))
+        .env("ARGO_CD_TOKEN", "argocd-token")
+        .env("VAULT_TOKEN", "root-token")
         .env("PROPELLER_LOG_LEVEL", "info")
         .stdout(Stdio::piped())
         .assert()
         .failure()
-        .stderr(contains("Missing VAULT_TOKEN environment variable"));
+        .stderr(
+            contains("Failed to read path 'rotate/released/secret' - did you init Vault?").and(
+                contains("Released Vault lock 'rotate/released/secret.lock'"),
+            ),
+        );
+
+    assert!(
+        kv2::read::<serde_json::Value>(&vault_client, "secret", "rotate/released/secret.lock")
+            .await
+            .is_err(),
+        "Expected the Vault lock to be released"
+    );
 }
 
 #[tokio::test]
-#[timeout(30_000)]
-async fn rotate_invalid_initialized_secret() {
-    let vault_container = vault_container().await;
+#[timeout(60_000)]
+async fn rotate_failed_rollout_keeps_vault_lock() {
+    let (postgres_container, vault_container) = join!(postgres_container(), vault_container());
 
-    let vault_host = vault_container.get_host().await.unwrap();
-    let vault_port = vault_container.get_host_port_ipv4(8200).await.unwrap();
+    let (postgres_host, postgres_port, vault_host, vault_port) = join!(
+        postgres_container.get_host(),
+        postgres_container.get_host_port_ipv4(5432),
+        vault_container.get_host(),
+        vault_container.get_host_port_ipv4(8200)
+    );
 
-    let vault_client = create_vault_client(vault_host.to_string().as_str(), vault_port);
-    create_invalid_vault_secret_path(&vault_client, "rotate/invalid/initialized/secret").await;
+    let postgres_host = postgres_host.unwrap().to_string();
+    let postgres_port = postgres_port.unwrap().to_string();
+    let vault_host = vault_host.unwrap().to_string();
+    let vault_port = vault_port.unwrap();
+
+    let vault_client = create_vault_client(&vault_host, vault_port);
+    let (_, postgres_client) = join!(
+        reset_vault_secret_path(&vault_client, "rotate/held/secret"),
+        connect_postgres_client(&postgres_host, &postgres_port, "demo", "demo_password",)
+    );
+
+    join!(
+        reset_role_initial_password(&postgres_client, "user1"),
+        reset_role_initial_password(&postgres_client, "user2")
+    );
 
     println!("Setup success; invoking propeller...");
 
+    // ArgoCD is not reachable, so the rollout fails after the switched secret has been written
     Command::cargo_bin("propeller")
         .unwrap()
         .arg("rotate")
@@ -266,39 +1586,69 @@ async fn rotate_invalid_initialized_secret() {
                 "
 argo_cd:
   application: 'propeller'
-  base_url: 'http://localhost:8080'
+  base_url: 'http://localhost:1'
+lock:
+  backend: 'vault'
 postgres:
-  host: 'localhost'
-  port: 5432
+  host: '{postgres_host}'
+  port: {postgres_port}
   database: 'demo'
 vault:
   base_url: 'http://{vault_host}:{vault_port}'
-  path: 'rotate/invalid/initialized/secret'
+  path: 'rotate/held/secret'
 "
             )
             .as_str(),
         ))
+        .env("ARGO_CD_TOKEN", "argocd-token")
         .env("VAULT_TOKEN", "root-token")
         .env("PROPELLER_LOG_LEVEL", "info")
         .stdout(Stdio::piped())
         .assert()
         .failure()
-        .stderr(contains(
-            "Failed to detect active user - did neither match user 1 nor 2",
-        ));
+        .stderr(
+            contains("Failed to sync ArgoCD")
+                .and(contains(
+                    "Keeping Vault lock 'rotate/held/secret.lock' until it expires",
+                ))
+                .and(contains("Released Vault lock").not()),
+        );
+
+    let vault_secret = read_vault_secret(&vault_client, "rotate/held/secret").await;
+
+    assert_eq!(vault_secret.postgresql_active_user, "user2");
+
+    assert!(
+        kv2::read::<serde_json::Value>(&vault_client, "secret", "rotate/held/secret.lock")
+            .await
+            .is_ok(),
+        "Expected the Vault lock to be kept"
+    );
 }
 
-#[tokio::test]
-#[timeout(30_000)]
-async fn rotate_non_existing_secret() {
-    let vault_container = vault_container().await;
+/// Deploys ArgoCD with the test application, rotates the secrets at `secret_path` of the given database target and
+/// returns the rotated secret along with the log output of propeller.
+async fn rotate_secrets_with_database_target(
+    k3s_container: &ContainerAsync<K3s>,
+    vault_client: &VaultClient,
+    vault_url: &str,
+    secret_path: &str,
+    database_target: &str,
+) -> (VaultSecret, String) {
+    let kubectl = get_kube_client(k3s_container).await;
 
-    let vault_host = vault_container.get_host().await.unwrap();
-    let vault_port = vault_container.get_host_port_ipv4(8200).await.unwrap();
+    deploy_argocd_and_wait_until_ready(&kubectl).await;
+
+    let (argocd_port, stop_sender) = open_argocd_server_port_forward(&kubectl).await;
+
+    let argocd_url = format!("https://localhost:{argocd_port}");
+
+    let argocd_token = get_argocd_access_token(&kubectl, argocd_url.as_str()).await;
+    create_argocd_application(argocd_url.as_str(), argocd_token.as_str()).await;
 
     println!("Setup success; invoking propeller...");
 
-    Command::cargo_bin("propeller")
+    let assert = Command::cargo_bin("propeller")
         .unwrap()
         .arg("rotate")
         .arg("-c")
@@ -306,28 +1656,36 @@ async fn rotate_non_existing_secret() {
             format!(
                 // language=yaml
                 "
-argo_cd:
-  application: 'propeller'
-  base_url: 'http://localhost:8080'
-postgres:
-  host: 'localhost'
-  port: 5432
-  database: 'demo'
-vault:
-  base_url: 'http://{vault_host}:{vault_port}'
-  path: 'rotate/non/existing/path'
+    argo_cd:
+      application: 'propeller'
+      base_url: 'https://localhost:{argocd_port}'
+      danger_accept_insecure: true{database_target}    vault:
+      base_url: '{vault_url}'
+      path: '{secret_path}'
 "
             )
             .as_str(),
         ))
+        .env("ARGO_CD_TOKEN", argocd_token)
         .env("VAULT_TOKEN", "root-token")
-        .env("PROPELLER_LOG_LEVEL", "info")
+        .env("PROPELLER_LOG_LEVEL", "debug,rustify=off")
         .stdout(Stdio::piped())
         .assert()
-        .failure()
-        .stderr(contains(
-            "Failed to read path 'rotate/non/existing/path' - did you init Vault?",
-        ));
+        .success()
+        .stdout(contains("Successfully rotated all secrets"));
+
+    let vault_secret = read_vault_secret(vault_client, secret_path).await;
+
+    assert_eq!(vault_secret.postgresql_active_user, "user2");
+    assert_ne!(vault_secret.postgresql_user_1_password, "initialpw");
+    assert_ne!(vault_secret.postgresql_user_2_password, "initialpw");
+
+    // Kill `kubectl port-forward` process
+    stop_sender.send(()).expect("Failed to send stop signal");
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).into_owned();
+
+    (vault_secret, stderr)
 }
 
 async fn reset_vault_secret_path(vault_client: &VaultClient, secret_path: &str) {
@@ -345,6 +1703,38 @@ async fn reset_vault_secret_path(vault_client: &VaultClient, secret_path: &str)
         .expect("Failed to reset Vault secret path");
 }
 
+async fn reset_kubernetes_secret(secrets: &Api<Secret>, name: &str) {
+    let secret = Secret {
+        metadata: ObjectMeta {
+            name: Some(name.to_string()),
+            ..ObjectMeta::default()
+        },
+        string_data: Some(BTreeMap::from([
+            ("postgresql_active_user".to_string(), "user1".to_string()),
+            (
+                "postgresql_active_user_password".to_string(),
+                "initialpw".to_string(),
+            ),
+            ("postgresql_user_1".to_string(), "user1".to_string()),
+            (
+                "postgresql_user_1_password".to_string(),
+                "initialpw".to_string(),
+            ),
+            ("postgresql_user_2".to_string(), "user2".to_string()),
+            (
+                "postgresql_user_2_password".to_string(),
+                "initialpw".to_string(),
+            ),
+        ])),
+        ..Secret::default()
+    };
+
+    secrets
+        .create(&PostParams::default(), &secret)
+        .await
+        .expect("Failed to create Kubernetes Secret");
+}
+
 async fn create_invalid_vault_secret_path(vault_client: &VaultClient, secret_path: &str) {
     let initial_secret = VaultSecret {
         postgresql_active_user: "userX".to_string(), // Note that 'userX' does neither match 'user1' nor 'user2'
@@ -383,6 +1773,51 @@ async fn connect_postgres_client(
     client
 }
 
+async fn await_postgres_client(
+    host: &str,
+    port: &str,
+    database: &str,
+    user: &str,
+    password: &str,
+) -> tokio_postgres::Client {
+    // The database might still be bootstrapping users although the container reports to be ready
+    let iteration_duration = Duration::from_secs(3);
+    let timeout_duration = Duration::from_secs(120);
+
+    let start_time = Instant::now();
+
+    loop {
+        match tokio_postgres::connect(
+            format!("host={host} port={port} dbname={database} user={user} password={password}")
+                .as_str(),
+            NoTls,
+        )
+        .await
+        {
+            Ok((client, connection)) => {
+                spawn(async move {
+                    if let Err(e) = connection.await {
+                        panic!("Failed to connect to to PostgreSQL: {}", e);
+                    }
+                });
+
+                return client;
+            }
+            Err(e) => {
+                if start_time.elapsed() >= timeout_duration {
+                    panic!(
+                        "Failed to connect to database after {} seconds: {}",
+                        timeout_duration.as_secs(),
+                        e
+                    );
+                }
+            }
+        }
+
+        sleep(iteration_duration).await;
+    }
+}
+
 async fn reset_role_initial_password(postgres_client: &tokio_postgres::Client, role: &str) {
     match postgres_client
         .execute(
@@ -404,7 +1839,101 @@ async fn reset_role_initial_password(postgres_client: &tokio_postgres::Client, r
     }
 }
 
+async fn reset_clickhouse_user_initial_password(clickhouse_url: &str, user: &str) {
+    execute_clickhouse_query(
+        clickhouse_url,
+        "demo",
+        "demo_password",
+        format!("CREATE USER OR REPLACE {user} IDENTIFIED WITH sha256_password BY 'initialpw'")
+            .as_str(),
+    )
+    .await;
+
+    // Users must be allowed to change their own password; ClickHouse only grants `ALTER USER` globally
+    execute_clickhouse_query(
+        clickhouse_url,
+        "demo",
+        "demo_password",
+        format!("GRANT ALTER USER ON *.* TO {user}").as_str(),
+    )
+    .await;
+}
+
+async fn execute_clickhouse_query(clickhouse_url: &str, user: &str, password: &str, query: &str) {
+    let response = Client::new()
+        .post(clickhouse_url)
+        .header("X-ClickHouse-User", user)
+        .header("X-ClickHouse-Key", password)
+        .body(query.to_string())
+        .send()
+        .await
+        .expect("Failed to send ClickHouse query");
+
+    assert!(
+        response.status().is_success(),
+        "Failed to execute ClickHouse query '{query}' as '{user}': {}",
+        response.text().await.unwrap_or_default()
+    );
+}
+
+async fn await_cassandra_session(contact_point: &str, user: &str, password: &str) -> Session {
+    // The default superuser is created in the background, after the node started serving
+    let iteration_duration = Duration::from_secs(3);
+    let timeout_duration = Duration::from_secs(120);
+
+    let start_time = Instant::now();
+
+    loop {
+        match SessionBuilder::new()
+            .known_node(contact_point)
+            .user(user, password)
+            .build()
+            .await
+        {
+            Ok(session) => return session,
+            Err(e) => {
+                if start_time.elapsed() >= timeout_duration {
+                    panic!(
+                        "Failed to connect to Cassandra after {} seconds: {}",
+                        timeout_duration.as_secs(),
+                        e
+                    );
+                }
+            }
+        }
+
+        sleep(iteration_duration).await;
+    }
+}
+
+async fn reset_cassandra_role_initial_password(session: &Session, role: &str) {
+    session
+        .query_unpaged(
+            format!(
+                "CREATE ROLE IF NOT EXISTS {role} WITH PASSWORD = 'initialpw' AND LOGIN = true"
+            ),
+            (),
+        )
+        .await
+        .unwrap_or_else(|_| panic!("Failed to create '{role}'"));
+
+    session
+        .query_unpaged(format!("ALTER ROLE {role} WITH PASSWORD = 'initialpw'"), ())
+        .await
+        .unwrap_or_else(|_| panic!("Failed to reset '{role}'"));
+}
+
 async fn create_argocd_application(argocd_url: &str, auth_token: &str) {
+    create_named_argocd_application(argocd_url, auth_token, "propeller", "propeller").await;
+}
+
+/// Deploys the test application once more under another name, into its own namespace.
+async fn create_named_argocd_application(
+    argocd_url: &str,
+    auth_token: &str,
+    name: &str,
+    namespace: &str,
+) {
     // Create a custom http client that accepts self-signed ArgoCD certificate
     let insecure_client = Client::builder()
         .danger_accept_invalid_certs(true)
@@ -415,7 +1944,10 @@ async fn create_argocd_application(argocd_url: &str, auth_token: &str) {
 
     let argocd_application = json!({
         "metadata": {
-            "name": "propeller"
+            "name": name,
+            "labels": {
+                "app.kubernetes.io/part-of": "propeller"
+            }
         },
         "spec": {
             "project": "default",
@@ -428,13 +1960,14 @@ async fn create_argocd_application(argocd_url: &str, auth_token: &str) {
             },
             "destination": {
                 "server": "https://kubernetes.default.svc",
-                "namespace": "propeller"
+                "namespace": namespace
             },
             "syncPolicy": {
                 "automated": {
                     "prune": true,
                     "selfHeal": true
-                }
+                },
+                "syncOptions": ["CreateNamespace=true"]
             }
         }
     });
@@ -469,7 +2002,58 @@ async fn create_argocd_application(argocd_url: &str, auth_token: &str) {
         sleep(iteration_duration).await;
     }
 
-    wait_for_argocd_application_rollout(argocd_url, &insecure_client, auth_token).await;
+    wait_for_argocd_application_rollout(argocd_url, &insecure_client, auth_token, name).await;
+}
+
+async fn start_argocd_sync(argocd_url: &str, auth_token: &str) {
+    // Create a custom http client that accepts self-signed ArgoCD certificate
+    let insecure_client = Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .expect("Failed to build custom http client for insecure ArgoCD connection");
+
+    let response = insecure_client
+        .post(format!("{argocd_url}/api/v1/applications/propeller/sync"))
+        .header("Authorization", format!("Bearer {auth_token}"))
+        .json(&json!({}))
+        .send()
+        .await
+        .expect("Failed to sync ArgoCD application");
+    assert!(
+        response.status().is_success(),
+        "Failed to sync ArgoCD application: {}",
+        response.text().await.unwrap_or_default()
+    );
+
+    let timeout_duration = Duration::from_secs(30);
+    let start_time = Instant::now();
+
+    loop {
+        if start_time.elapsed() >= timeout_duration {
+            panic!("Timeout reached while waiting for ArgoCD operation to start");
+        }
+
+        let app_information: Application = insecure_client
+            .get(format!("{argocd_url}/api/v1/applications/propeller"))
+            .header("Authorization", format!("Bearer {auth_token}"))
+            .send()
+            .await
+            .expect("Failed to request ArgoCD operation state")
+            .json()
+            .await
+            .expect("Failed to read ArgoCD operation state response");
+
+        if app_information
+            .status
+            .operationState
+            .and_then(|operation_state| operation_state.phase)
+            .is_some_and(|phase| phase == "Running")
+        {
+            return;
+        }
+
+        sleep(Duration::from_secs(1)).await;
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -482,6 +2066,7 @@ struct Application {
 struct ApplicationStatus {
     sync: SyncStatus,
     health: HealthStatus,
+    operationState: Option<OperationState>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -494,12 +2079,18 @@ struct HealthStatus {
     status: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct OperationState {
+    phase: Option<String>,
+}
+
 async fn wait_for_argocd_application_rollout(
     argocd_url: &str,
     client: &Client,
     argocd_token: &str,
+    name: &str,
 ) {
-    let url = format!("{argocd_url}/api/v1/applications/propeller",);
+    let url = format!("{argocd_url}/api/v1/applications/{name}");
 
     let request = client
         .get(url.as_str())
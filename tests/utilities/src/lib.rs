@@ -3,6 +3,8 @@
 // This software is released under the MIT License.
 // https://opensource.org/licenses/MIT
 
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_secretsmanager::config::Credentials;
 use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
 use base64::Engine;
 use futures::StreamExt;
@@ -27,10 +29,17 @@ use std::io::Write;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
+use testcontainers_modules::clickhouse::ClickHouse;
+use testcontainers_modules::cockroach_db::CockroachDb;
 use testcontainers_modules::hashicorp_vault::HashicorpVault;
 use testcontainers_modules::k3s::{K3s, KUBE_SECURE_PORT};
+use testcontainers_modules::localstack::LocalStack;
 use testcontainers_modules::postgres::Postgres;
-use testcontainers_modules::testcontainers::{runners::AsyncRunner, ContainerAsync, ImageExt};
+use testcontainers_modules::scylladb::ScyllaDB;
+use testcontainers_modules::testcontainers::core::{IntoContainerPort, WaitFor};
+use testcontainers_modules::testcontainers::{
+    runners::AsyncRunner, ContainerAsync, GenericImage, ImageExt,
+};
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpListener;
 use tokio::sync::oneshot;
@@ -54,6 +63,55 @@ pub async fn postgres_container() -> ContainerAsync<Postgres> {
         .expect("Failed to launch PostgreSQL database")
 }
 
+pub async fn clickhouse_container() -> ContainerAsync<ClickHouse> {
+    ClickHouse::default()
+        .with_env_var("CLICKHOUSE_USER", "demo")
+        .with_env_var("CLICKHOUSE_PASSWORD", "demo_password")
+        .with_env_var("CLICKHOUSE_DEFAULT_ACCESS_MANAGEMENT", "1")
+        .with_userns_mode("host")
+        .start()
+        .await
+        .expect("Failed to launch ClickHouse")
+}
+
+pub async fn cockroachdb_container() -> ContainerAsync<CockroachDb> {
+    // Passwords can only be set in secure mode; certificates are generated by the image itself
+    CockroachDb::default()
+        .with_cmd(["start-single-node", "--accept-sql-without-tls"])
+        .with_env_var("COCKROACH_DATABASE", "demo")
+        .with_env_var("COCKROACH_USER", "demo")
+        .with_env_var("COCKROACH_PASSWORD", "demo_password")
+        .with_userns_mode("host")
+        .start()
+        .await
+        .expect("Failed to launch CockroachDB")
+}
+
+pub async fn yugabytedb_container() -> ContainerAsync<GenericImage> {
+    GenericImage::new("yugabytedb/yugabyte", "2.25.1.0-b381")
+        .with_exposed_port(5433.tcp())
+        .with_wait_for(WaitFor::message_on_stdout("YugabyteDB Started"))
+        .with_cmd([
+            "bin/yugabyted",
+            "start",
+            "--background=false",
+            "--tserver_flags=ysql_enable_auth=true",
+        ])
+        .with_userns_mode("host")
+        .start()
+        .await
+        .expect("Failed to launch YugabyteDB")
+}
+
+pub async fn scylladb_container() -> ContainerAsync<ScyllaDB> {
+    ScyllaDB::default()
+        .with_cmd(["--smp", "1", "--authenticator", "PasswordAuthenticator"])
+        .with_userns_mode("host")
+        .start()
+        .await
+        .expect("Failed to launch ScyllaDB")
+}
+
 pub async fn vault_container() -> ContainerAsync<HashicorpVault> {
     HashicorpVault::default()
         .with_env_var("VAULT_DEV_ROOT_TOKEN_ID", "root-token")
@@ -63,6 +121,15 @@ pub async fn vault_container() -> ContainerAsync<HashicorpVault> {
         .expect("Failed to launch Vault")
 }
 
+pub async fn localstack_container() -> ContainerAsync<LocalStack> {
+    LocalStack::default()
+        .with_env_var("SERVICES", "secretsmanager")
+        .with_userns_mode("host")
+        .start()
+        .await
+        .expect("Failed to launch LocalStack")
+}
+
 pub async fn k3s_container() -> ContainerAsync<K3s> {
     let conf_dir = temp_dir();
     K3s::default()
@@ -90,6 +157,17 @@ pub async fn get_kube_client(container: &ContainerAsync<K3s>) -> Client {
             .expect("Error initializing rustls provider");
     }
 
+    let config = get_kube_config(container).await;
+
+    let client_config = Config::from_custom_kubeconfig(config, &KubeConfigOptions::default())
+        .await
+        .expect("Failed to create client config from kube config");
+
+    kube::Client::try_from(client_config).expect("Failed to create client from client config")
+}
+
+/// The kubeconfig of the k3s container, pointing to its port exposed on the host.
+pub async fn get_kube_config(container: &ContainerAsync<K3s>) -> Kubeconfig {
     let conf_yaml = container
         .image()
         .read_kube_config()
@@ -110,11 +188,7 @@ pub async fn get_kube_client(container: &ContainerAsync<K3s>) -> Client {
         }
     });
 
-    let client_config = Config::from_custom_kubeconfig(config, &KubeConfigOptions::default())
-        .await
-        .expect("Failed to create client config from kube config");
-
-    kube::Client::try_from(client_config).expect("Failed to create client from client config")
+    config
 }
 
 pub async fn deploy_argocd_and_wait_until_ready(kubectl: &Client) {
@@ -342,7 +416,7 @@ struct LoginResponse {
     token: String,
 }
 
-pub async fn get_argocd_access_token(kubectl: &Client, argocd_url: &str) -> String {
+pub async fn get_argocd_admin_password(kubectl: &Client) -> String {
     let secrets: Api<Secret> = Api::namespaced(kubectl.clone(), ARGOCD_NAMESPACE);
 
     let secret = secrets
@@ -354,7 +428,7 @@ pub async fn get_argocd_access_token(kubectl: &Client, argocd_url: &str) -> Stri
         .and_then(|mut data| data.remove("password"))
         .expect("Failed to react password from initial admin secret");
 
-    let password = match String::from_utf8(password_data.0.clone()) {
+    match String::from_utf8(password_data.0.clone()) {
         Ok(password) => password,
         Err(_) => {
             let decoded = BASE64_STANDARD
@@ -362,7 +436,11 @@ pub async fn get_argocd_access_token(kubectl: &Client, argocd_url: &str) -> Stri
                 .expect("Failed to decode base64 string");
             String::from_utf8(decoded).expect("Failed to extract initial admin password")
         }
-    };
+    }
+}
+
+pub async fn get_argocd_access_token(kubectl: &Client, argocd_url: &str) -> String {
+    let password = get_argocd_admin_password(kubectl).await;
 
     // Create a custom http client that accepts self-signed ArgoCD certificate
     let insecure_client = reqwest::Client::builder()
@@ -428,6 +506,57 @@ pub async fn read_vault_secret(vault_client: &VaultClient, secret_path: &str) ->
     .expect("Failed to parse Vault secret")
 }
 
+pub async fn read_aws_secret(endpoint: &str, secret_id: &str) -> VaultSecret {
+    let secret_value = create_aws_secrets_manager_client(endpoint)
+        .await
+        .get_secret_value()
+        .secret_id(secret_id)
+        .send()
+        .await
+        .expect("Failed to read AWS secret");
+
+    serde_json::from_str(
+        secret_value
+            .secret_string()
+            .expect("Missing secret string in AWS secret"),
+    )
+    .expect("Failed to parse AWS secret")
+}
+
+pub async fn write_aws_secret(endpoint: &str, secret_id: &str, secret: &VaultSecret) {
+    let client = create_aws_secrets_manager_client(endpoint).await;
+    let secret_string = serde_json::to_string(secret).expect("Failed to serialize AWS secret");
+
+    let created = client
+        .create_secret()
+        .name(secret_id)
+        .secret_string(secret_string.as_str())
+        .send()
+        .await;
+
+    if created.is_err() {
+        client
+            .put_secret_value()
+            .secret_id(secret_id)
+            .secret_string(secret_string)
+            .send()
+            .await
+            .expect("Failed to write AWS secret");
+    }
+}
+
+/// LocalStack accepts any credentials, as long as the requests are signed.
+async fn create_aws_secrets_manager_client(endpoint: &str) -> aws_sdk_secretsmanager::Client {
+    let sdk_config = aws_config::defaults(BehaviorVersion::latest())
+        .region(Region::new("eu-central-2"))
+        .endpoint_url(endpoint)
+        .credentials_provider(Credentials::new("test", "test", None, None, "test"))
+        .load()
+        .await;
+
+    aws_sdk_secretsmanager::Client::new(&sdk_config)
+}
+
 pub fn write_string_to_tempfile(content: &str) -> String {
     let mut dir = temp_dir();
     let filename = format!("temp_file_{suffix}", suffix = random::<u64>());